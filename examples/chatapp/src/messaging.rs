@@ -5,7 +5,7 @@ use myriam::{
             address::ActorAddress, dencoder::bitcode::BitcodeDencoder,
             netlayer::tor_layer::TorLayer, router::RemoteHandle,
         },
-        Actor,
+        Actor, Context,
     },
     messaging::Message,
 };
@@ -49,7 +49,7 @@ pub enum MessengerCmd {
 // actor impls
 //
 impl Actor<MessengerCmd, (), AppError> for Messenger {
-    async fn handler(&self, input: MessengerCmd) -> Result<(), AppError> {
+    async fn handler(&self, input: MessengerCmd, _ctx: &Context) -> Result<(), AppError> {
         match input {
             MessengerCmd::Incoming(msg) => {
                 if self.peer.is_some() {
@@ -74,7 +74,11 @@ impl Actor<MessengerCmd, (), AppError> for Messenger {
         }
     }
 
-    async fn handler_mut(&mut self, input: MessengerCmd) -> Result<Option<()>, AppError> {
+    async fn handler_mut(
+        &mut self,
+        input: MessengerCmd,
+        _ctx: &Context,
+    ) -> Result<Option<()>, AppError> {
         match input {
             MessengerCmd::Register(addr) => {
                 if self.peer.is_some() {
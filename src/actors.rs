@@ -2,40 +2,183 @@
 //! root module for local and remote actors
 //!
 
-use std::future::Future;
+use std::{future::Future, sync::Arc};
+
+use tokio_util::sync::CancellationToken;
+
+use crate::messaging::{MsgError, ReplyWriter};
 
 pub mod local;
 
 #[cfg(feature = "remote")]
 pub mod remote;
 
+#[cfg(feature = "remote")]
+pub mod status;
+
 ///
 /// main actor trait
 ///
 /// type parameters `I`, `O` and `E` correspond to the handler's input, output and error, respectively.
+/// `C` is the type of the shared [`Context`] handlers get, set when spawning.
 ///
-pub trait Actor<I, O, E> {
+pub trait Actor<I, O, E, C = ()> {
     ///
     /// this actor's message handler
     ///
-    fn handler(&self, input: I) -> impl Future<Output = Result<O, E>> + Send;
+    fn handler(&self, input: I, ctx: &Context<C>) -> impl Future<Output = Result<O, E>> + Send;
 
     ///
     /// this actor's message handler with requested mutation
     ///
-    fn handler_mut(&mut self, _input: I) -> impl Future<Output = Result<Option<O>, E>> + Send {
+    fn handler_mut(
+        &mut self,
+        _input: I,
+        _ctx: &Context<C>,
+    ) -> impl Future<Output = Result<Option<O>, E>> + Send {
         async { Ok(None) }
     }
+
+    ///
+    /// this actor's message handler for streamed replies, writing its output in chunks to `writer`
+    /// instead of buffering it whole.
+    ///
+    /// not supported by default.
+    ///
+    fn handler_streaming(
+        &self,
+        _input: I,
+        _ctx: &Context<C>,
+        _writer: ReplyWriter,
+    ) -> impl Future<Output = Result<(), MsgError<E>>> + Send {
+        async { Err(MsgError::NotSupported) }
+    }
+
+    ///
+    /// whether this actor agrees to stop, e.g. false while holding uncommitted state.
+    ///
+    /// refused stops are answered with [`MsgError::StopRefused`]
+    /// and the actor keeps running. true by default.
+    ///
+    fn can_stop(&self) -> impl Future<Output = bool> + Send {
+        async { true }
+    }
+
+    ///
+    /// whether this actor handles `TaskMut` at all. if not, those are refused with
+    /// [`MsgError::NotSupported`] instead of reaching [`Self::handler_mut()`]. true by default.
+    ///
+    fn supports_mut(&self) -> bool {
+        true
+    }
+
+    ///
+    /// serialized snapshot of this actor's state, for debugging. sent in reply to `Message::Snapshot`.
+    ///
+    /// not supported (none) by default.
+    ///
+    fn snapshot(&self) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+///
+/// actors shared through an [`Arc`] are actors too, e.g. to keep a large read-only resource
+/// around without cloning it into the actor.
+///
+/// mutation isn't possible through a shared actor, so `TaskMut` is refused with
+/// [`MsgError::NotSupported`].
+///
+impl<A, I, O, E, C> Actor<I, O, E, C> for Arc<A>
+where
+    A: Actor<I, O, E, C> + Send + Sync,
+{
+    fn handler(&self, input: I, ctx: &Context<C>) -> impl Future<Output = Result<O, E>> + Send {
+        self.as_ref().handler(input, ctx)
+    }
+
+    fn handler_streaming(
+        &self,
+        input: I,
+        ctx: &Context<C>,
+        writer: ReplyWriter,
+    ) -> impl Future<Output = Result<(), MsgError<E>>> + Send {
+        self.as_ref().handler_streaming(input, ctx, writer)
+    }
+
+    fn can_stop(&self) -> impl Future<Output = bool> + Send {
+        self.as_ref().can_stop()
+    }
+
+    fn supports_mut(&self) -> bool {
+        false
+    }
+
+    fn snapshot(&self) -> Option<Vec<u8>> {
+        self.as_ref().snapshot()
+    }
+}
+
+///
+/// read-only context shared with an actor's handlers, e.g. configuration or a connection pool
+///
+#[derive(Debug)]
+pub struct Context<C = ()> {
+    shared: Arc<C>,
+    cancellation: CancellationToken,
+}
+
+impl<C> Context<C> {
+    /// wrap `shared` in a new context
+    pub fn new(shared: C) -> Self {
+        Self {
+            shared: Arc::new(shared),
+            cancellation: CancellationToken::new(),
+        }
+    }
+
+    /// shared value this context was created with
+    pub fn shared(&self) -> &C {
+        &self.shared
+    }
+
+    ///
+    /// token cancelled once the sender of the message being handled stops waiting for its reply,
+    /// or the actor is asked to stop meanwhile (see [`local::spawn_concurrent()`]).
+    ///
+    /// handlers are never interrupted, long-running ones can check it to give up early.
+    ///
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    /// same shared value, with a different cancellation token
+    pub(crate) fn with_token(&self, cancellation: CancellationToken) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            cancellation,
+        }
+    }
+}
+
+impl<C> Clone for Context<C> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            cancellation: self.cancellation.clone(),
+        }
+    }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use std::fmt::Display;
 
     #[cfg(feature = "remote")]
     use serde::{Deserialize, Serialize};
 
-    use super::Actor;
+    use super::{Actor, Context};
+    use crate::messaging::{MsgError, ReplyWriter};
 
     pub(crate) struct Mult {
         pub a: u32,
@@ -52,21 +195,69 @@ mod tests {
     }
 
     impl Actor<u32, u32, SomeError> for Mult {
-        async fn handler(&self, input: u32) -> Result<u32, SomeError> {
+        async fn handler(&self, input: u32, _ctx: &Context) -> Result<u32, SomeError> {
             Ok(input * self.a)
         }
 
-        async fn handler_mut(&mut self, input: u32) -> Result<Option<u32>, SomeError> {
+        async fn handler_mut(
+            &mut self,
+            input: u32,
+            _ctx: &Context,
+        ) -> Result<Option<u32>, SomeError> {
             self.a = input;
 
             Ok(None)
         }
     }
 
+    /// sleeps for `input` milliseconds before echoing it back
+    pub(crate) struct Sleeper;
+
+    impl Actor<u64, u64, SomeError> for Sleeper {
+        async fn handler(&self, input: u64, _ctx: &Context) -> Result<u64, SomeError> {
+            tokio::time::sleep(std::time::Duration::from_millis(input)).await;
+
+            Ok(input)
+        }
+    }
+
+    /// echoes its input back
+    #[cfg(feature = "tcp")]
+    pub(crate) struct Echo;
+
+    #[cfg(feature = "tcp")]
+    impl Actor<Vec<u8>, Vec<u8>, SomeError> for Echo {
+        async fn handler(&self, input: Vec<u8>, _ctx: &Context) -> Result<Vec<u8>, SomeError> {
+            Ok(input)
+        }
+    }
+
+    /// streams `input` chunks of 4 bytes, each filled with its index
+    pub(crate) struct Chunker;
+
+    impl Actor<u8, (), SomeError> for Chunker {
+        async fn handler(&self, _input: u8, _ctx: &Context) -> Result<(), SomeError> {
+            Ok(())
+        }
+
+        async fn handler_streaming(
+            &self,
+            input: u8,
+            _ctx: &Context,
+            writer: ReplyWriter,
+        ) -> Result<(), MsgError<SomeError>> {
+            for i in 0..input {
+                writer.write(vec![i; 4]).await?;
+            }
+
+            Ok(())
+        }
+    }
+
     #[tokio::test]
     async fn direct_message() {
         let a = Mult { a: 5 };
 
-        assert_eq!(10, a.handler(2).await.unwrap());
+        assert_eq!(10, a.handler(2, &Context::new(())).await.unwrap());
     }
 }
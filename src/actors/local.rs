@@ -2,44 +2,109 @@
 //! local actors, with no net dependencies
 //!
 
-use std::fmt::Display;
+use std::{
+    fmt::Display,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
 
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{RwLock, Semaphore, mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
 
-use crate::messaging::{Message, MsgError, MsgResult, Reply};
+use crate::messaging::{Message, MsgError, MsgResult, Reply, ReplyWriter, TypeSignature};
 
-use super::Actor;
+use crate::utils::spawn_task;
+
+use super::{Actor, Context};
+
+// TODO: non-arbitrary channel bound
+const DEFAULT_CAPACITY: usize = 1024;
 
 ///
 /// consume an actor and return a handle to it
 ///
 pub async fn spawn<I, O, E>(
-    mut actor: impl Actor<I, O, E> + Send + 'static,
+    actor: impl Actor<I, O, E> + Send + 'static,
 ) -> Result<LocalHandle<I, O, E>, Error>
 where
     I: Send + 'static,
     O: Send + 'static,
     E: Send + 'static,
 {
-    // TODO: non-arbitrary channel bound
-    let (sender, mut receiver) =
-        mpsc::channel::<(Message<I>, oneshot::Sender<MsgResult<O, E>>)>(1024);
+    spawn_with_context(actor, ()).await
+}
+
+///
+/// consume an actor and return a handle to it, queueing up to `capacity` messages for it.
+///
+/// see [`LocalHandle::try_send()`] for sending without waiting on a full queue.
+///
+pub async fn spawn_with_capacity<I, O, E>(
+    actor: impl Actor<I, O, E> + Send + 'static,
+    capacity: usize,
+) -> Result<LocalHandle<I, O, E>, Error>
+where
+    I: Send + 'static,
+    O: Send + 'static,
+    E: Send + 'static,
+{
+    spawn_inner(actor, (), capacity).await
+}
+
+///
+/// consume an actor and return a handle to it, sharing `ctx` with its handlers
+///
+pub async fn spawn_with_context<I, O, E, C>(
+    actor: impl Actor<I, O, E, C> + Send + 'static,
+    ctx: C,
+) -> Result<LocalHandle<I, O, E>, Error>
+where
+    I: Send + 'static,
+    O: Send + 'static,
+    E: Send + 'static,
+    C: Send + Sync + 'static,
+{
+    spawn_inner(actor, ctx, DEFAULT_CAPACITY).await
+}
+
+async fn spawn_inner<I, O, E, C>(
+    mut actor: impl Actor<I, O, E, C> + Send + 'static,
+    ctx: C,
+    capacity: usize,
+) -> Result<LocalHandle<I, O, E>, Error>
+where
+    I: Send + 'static,
+    O: Send + 'static,
+    E: Send + 'static,
+    C: Send + Sync + 'static,
+{
+    let ctx = Context::new(ctx);
+    let (sender, mut receiver) = mpsc::channel::<Envelope<I, O, E>>(capacity.max(1));
     let (conf_sender, conf_receiver) = oneshot::channel::<Result<(), Error>>();
 
-    tokio::spawn(async move {
+    spawn_task("myriam::local", async move {
         let _ = conf_sender.send(Ok(()));
-        while let Some((msg, sender)) = receiver.recv().await {
+        while let Some((msg, mut sender, writer)) = receiver.recv().await {
+            let ctx = ctx.with_token(CancellationToken::new());
+
             match msg {
                 Message::Task(input) => {
-                    let result = match actor.handler(input).await {
+                    let handled = actor.handler(input, &ctx);
+                    let result = match watch_caller(handled, &mut sender, &ctx).await {
                         Ok(res) => Ok(Reply::Task(res)),
                         Err(err) => Err(MsgError::Task(err)),
                     };
 
                     try_send_reply(sender, result);
                 }
+                Message::TaskMut(_) if !actor.supports_mut() => {
+                    try_send_reply(sender, Err(MsgError::NotSupported));
+                }
                 Message::TaskMut(input) => {
-                    let result = match actor.handler_mut(input).await {
+                    let handled = actor.handler_mut(input, &ctx);
+                    let result = match watch_caller(handled, &mut sender, &ctx).await {
                         Ok(Some(res)) => Ok(Reply::Task(res)),
                         Ok(None) => Ok(Reply::Accepted),
                         Err(err) => Err(MsgError::Task(err)),
@@ -47,13 +112,49 @@ where
 
                     try_send_reply(sender, result);
                 }
+                Message::Tell(input) => {
+                    try_send_reply(sender, Ok(Reply::Accepted));
+
+                    if actor.handler(input, &ctx).await.is_err() {
+                        tracing::debug!("local: told task failed");
+                    }
+                }
                 Message::Ping => {
                     try_send_reply(sender, Ok(Reply::Accepted));
                 }
                 Message::Stop => {
+                    if !actor.can_stop().await {
+                        try_send_reply(sender, Err(MsgError::StopRefused));
+                        continue;
+                    }
+
                     try_send_reply(sender, Ok(Reply::Accepted));
                     break;
                 }
+                Message::Snapshot => {
+                    let result = match actor.snapshot() {
+                        Some(snapshot) => Ok(Reply::Snapshot(snapshot)),
+                        None => Err(MsgError::NotSupported),
+                    };
+
+                    try_send_reply(sender, result);
+                }
+                Message::Describe => {
+                    try_send_reply(sender, Ok(Reply::Describe(TypeSignature::of::<I, O, E>())));
+                }
+                Message::Stream(input) => {
+                    let result = match writer {
+                        Some(writer) => {
+                            let handled = actor.handler_streaming(input, &ctx, writer);
+                            watch_caller(handled, &mut sender, &ctx)
+                                .await
+                                .map(|_| Reply::Accepted)
+                        }
+                        None => Err(MsgError::NotAllowed),
+                    };
+
+                    try_send_reply(sender, result);
+                }
             }
         }
     });
@@ -63,25 +164,207 @@ where
         .await
         .map_err(|e| Error::Spawn(e.to_string()))??;
 
-    Ok(LocalHandle { sender })
+    Ok(LocalHandle::new(sender))
+}
+
+///
+/// consume an actor and return a handle to it, running up to `max` immutable handlers at once.
+///
+/// `Task`, `Tell` and `Stream` messages are handled in their own tasks. `TaskMut` and `Stop`
+/// wait for those in flight to finish, and hold later messages back until they're done.
+///
+/// a `Stop` the actor accepts (see [`Actor::can_stop()`]) cancels the context token of the
+/// handlers in flight before waiting for them. a refused one leaves them alone.
+///
+pub async fn spawn_concurrent<I, O, E>(
+    actor: impl Actor<I, O, E> + Send + Sync + 'static,
+    max: usize,
+) -> Result<LocalHandle<I, O, E>, Error>
+where
+    I: Send + 'static,
+    O: Send + 'static,
+    E: Send + 'static,
+{
+    let ctx = Context::new(());
+    let actor = Arc::new(RwLock::new(actor));
+    let in_flight = Arc::new(Semaphore::new(max.max(1)));
+
+    let (sender, mut receiver) = mpsc::channel::<Envelope<I, O, E>>(DEFAULT_CAPACITY);
+    let (conf_sender, conf_receiver) = oneshot::channel::<Result<(), Error>>();
+
+    spawn_task("myriam::local::concurrent", async move {
+        let _ = conf_sender.send(Ok(()));
+        // cancelled once stopping, so handlers in flight can wrap up
+        let stopping = CancellationToken::new();
+
+        while let Some((msg, mut sender, writer)) = receiver.recv().await {
+            let ctx = ctx.with_token(stopping.child_token());
+
+            match msg {
+                Message::Task(_) | Message::Tell(_) | Message::Stream(_) => {
+                    let permit = in_flight
+                        .clone()
+                        .acquire_owned()
+                        .await
+                        .expect("semaphore is never closed");
+                    let actor = actor.clone().read_owned().await;
+
+                    spawn_task("myriam::local::concurrent::handler", async move {
+                        match msg {
+                            Message::Task(input) => {
+                                let handled = actor.handler(input, &ctx);
+                                let result = match watch_caller(handled, &mut sender, &ctx).await {
+                                    Ok(res) => Ok(Reply::Task(res)),
+                                    Err(err) => Err(MsgError::Task(err)),
+                                };
+
+                                try_send_reply(sender, result);
+                            }
+                            Message::Tell(input) => {
+                                try_send_reply(sender, Ok(Reply::Accepted));
+
+                                if actor.handler(input, &ctx).await.is_err() {
+                                    tracing::debug!("local: told task failed");
+                                }
+                            }
+                            Message::Stream(input) => {
+                                let result = match writer {
+                                    Some(writer) => {
+                                        let handled = actor.handler_streaming(input, &ctx, writer);
+                                        watch_caller(handled, &mut sender, &ctx)
+                                            .await
+                                            .map(|_| Reply::Accepted)
+                                    }
+                                    None => Err(MsgError::NotAllowed),
+                                };
+
+                                try_send_reply(sender, result);
+                            }
+                            _ => unreachable!("only immutable messages are handled concurrently"),
+                        }
+
+                        drop(permit);
+                    });
+                }
+                Message::TaskMut(input) => {
+                    let mut actor = actor.write().await;
+                    if !actor.supports_mut() {
+                        try_send_reply(sender, Err(MsgError::NotSupported));
+                        continue;
+                    }
+
+                    let handled = actor.handler_mut(input, &ctx);
+                    let result = match watch_caller(handled, &mut sender, &ctx).await {
+                        Ok(Some(res)) => Ok(Reply::Task(res)),
+                        Ok(None) => Ok(Reply::Accepted),
+                        Err(err) => Err(MsgError::Task(err)),
+                    };
+
+                    try_send_reply(sender, result);
+                }
+                Message::Stop => {
+                    if !actor.read().await.can_stop().await {
+                        try_send_reply(sender, Err(MsgError::StopRefused));
+                        continue;
+                    }
+
+                    // handlers in flight hold read locks, so this waits for them
+                    stopping.cancel();
+                    let _ = actor.write().await;
+
+                    try_send_reply(sender, Ok(Reply::Accepted));
+                    break;
+                }
+                Message::Ping => {
+                    try_send_reply(sender, Ok(Reply::Accepted));
+                }
+                Message::Snapshot => {
+                    let result = match actor.read().await.snapshot() {
+                        Some(snapshot) => Ok(Reply::Snapshot(snapshot)),
+                        None => Err(MsgError::NotSupported),
+                    };
+
+                    try_send_reply(sender, result);
+                }
+                Message::Describe => {
+                    try_send_reply(sender, Ok(Reply::Describe(TypeSignature::of::<I, O, E>())));
+                }
+            }
+        }
+    });
+
+    conf_receiver
+        .await
+        .map_err(|e| Error::Spawn(e.to_string()))??;
+
+    Ok(LocalHandle::new(sender))
+}
+
+///
+/// wait for a handler, cancelling its context's token if the caller stops waiting for the reply
+///
+async fn watch_caller<T, R, C>(
+    handled: impl Future<Output = T>,
+    reply: &mut Option<oneshot::Sender<R>>,
+    ctx: &Context<C>,
+) -> T {
+    let Some(reply) = reply else {
+        return handled.await;
+    };
+
+    tokio::pin!(handled);
+
+    tokio::select! {
+        res = &mut handled => res,
+        _ = reply.closed() => {
+            ctx.cancellation_token().cancel();
+            handled.await
+        }
+    }
 }
 
-fn try_send_reply<O, E>(sender: oneshot::Sender<MsgResult<O, E>>, reply: MsgResult<O, E>) {
-    if sender.send(reply).is_err() {
+fn try_send_reply<O, E>(sender: Option<oneshot::Sender<MsgResult<O, E>>>, reply: MsgResult<O, E>) {
+    // notifications don't expect a reply
+    if let Some(sender) = sender
+        && sender.send(reply).is_err()
+    {
         tracing::error!("local: failed to send reply");
     }
 }
 
+/// message, where to reply (if anywhere) and where to stream chunks (if streaming)
+type Envelope<I, O, E> = (
+    Message<I>,
+    Option<oneshot::Sender<MsgResult<O, E>>>,
+    Option<ReplyWriter>,
+);
+
 ///
 /// handle for a locally spawned actor
 ///
-#[derive(Debug, Clone)]
-#[allow(clippy::type_complexity)]
+#[derive(Debug)]
 pub struct LocalHandle<I, O, E> {
-    sender: mpsc::Sender<(Message<I>, oneshot::Sender<MsgResult<O, E>>)>,
+    sender: mpsc::Sender<Envelope<I, O, E>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl<I, O, E> Clone for LocalHandle<I, O, E> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            dropped: self.dropped.clone(),
+        }
+    }
 }
 
 impl<I, O, E> LocalHandle<I, O, E> {
+    fn new(sender: mpsc::Sender<Envelope<I, O, E>>) -> Self {
+        Self {
+            sender,
+            dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
     ///
     /// attempt to send a message to this actor
     ///
@@ -89,13 +372,66 @@ impl<I, O, E> LocalHandle<I, O, E> {
         let (sender, receiver) = oneshot::channel();
 
         self.sender
-            .send((msg, sender))
+            .send((msg, Some(sender), None))
             .await
             .map_err(|e| MsgError::Send(e.to_string()))?;
 
         receiver.await.map_err(|e| MsgError::Recv(e.to_string()))?
     }
 
+    ///
+    /// send `input` as a [`Message::Stream`], the actor writing its output to `writer`.
+    ///
+    /// resolves once the handler is done, so chunks should be read concurrently
+    /// or they might fill the writer's buffer.
+    ///
+    pub async fn send_streaming(&self, input: I, writer: ReplyWriter) -> MsgResult<O, E> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send((Message::Stream(input), Some(sender), Some(writer)))
+            .await
+            .map_err(|e| MsgError::Send(e.to_string()))?;
+
+        receiver.await.map_err(|e| MsgError::Recv(e.to_string()))?
+    }
+
+    ///
+    /// send a message to this actor without waiting for (or even receiving) its reply
+    ///
+    pub async fn notify(&self, msg: Message<I>) -> Result<(), MsgError<E>> {
+        self.sender
+            .send((msg, None, None))
+            .await
+            .map_err(|e| MsgError::Send(e.to_string()))
+    }
+
+    ///
+    /// like [`Self::notify()`], but fails right away with [`MsgError::Busy`] if the actor's queue
+    /// is full, dropping the message.
+    ///
+    /// dropped messages are counted, see [`Self::dropped_count()`].
+    ///
+    pub fn try_send(&self, msg: Message<I>) -> Result<(), MsgError<E>> {
+        self.sender
+            .try_send((msg, None, None))
+            .map_err(|e| match e {
+                mpsc::error::TrySendError::Full(_) => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    MsgError::Busy
+                }
+                mpsc::error::TrySendError::Closed(_) => MsgError::Send(e.to_string()),
+            })
+    }
+
+    ///
+    /// messages dropped by [`Self::try_send()`] because the actor's queue was full,
+    /// across every handle to this actor.
+    ///
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
     ///
     /// attempt to send a message to this actor
     ///
@@ -105,7 +441,7 @@ impl<I, O, E> LocalHandle<I, O, E> {
         let (sender, receiver) = oneshot::channel();
 
         self.sender
-            .blocking_send((msg, sender))
+            .blocking_send((msg, Some(sender), None))
             .map_err(|e| MsgError::Send(e.to_string()))?;
 
         receiver
@@ -135,15 +471,44 @@ impl std::error::Error for Error {}
 
 #[cfg(test)]
 mod tests {
-    use std::time::Duration;
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::{Duration, Instant},
+    };
 
     use tokio::{runtime::Runtime, sync::oneshot};
 
     use crate::{
-        actors::tests::Mult,
-        messaging::{Message, Reply},
+        actors::{
+            Actor, Context,
+            tests::{Chunker, Mult, Sleeper, SomeError},
+        },
+        messaging::{Message, MsgError, Reply, ReplyWriter},
     };
 
+    struct Counter {
+        count: u32,
+    }
+
+    impl Actor<u32, u32, SomeError> for Counter {
+        async fn handler(&self, _input: u32, _ctx: &Context) -> Result<u32, SomeError> {
+            Ok(self.count)
+        }
+
+        async fn handler_mut(
+            &mut self,
+            input: u32,
+            _ctx: &Context,
+        ) -> Result<Option<u32>, SomeError> {
+            self.count += input;
+
+            Ok(None)
+        }
+    }
+
     #[tokio::test]
     async fn spawning_and_messaging() {
         let mult = Mult { a: 2 };
@@ -180,6 +545,19 @@ mod tests {
         handle.send(Message::Ping).await.unwrap_err();
     }
 
+    #[tokio::test]
+    async fn notify() {
+        let handle = super::spawn(Counter { count: 0 }).await.unwrap();
+
+        for _ in 0..500 {
+            handle.notify(Message::TaskMut(1)).await.unwrap();
+        }
+
+        let reply = handle.send(Message::Task(0)).await.unwrap();
+
+        assert!(matches!(reply, Reply::Task(500)));
+    }
+
     #[test]
     fn blocking_send() {
         let rt = Runtime::new().unwrap();
@@ -204,4 +582,298 @@ mod tests {
 
         compl_sender.send(()).unwrap();
     }
+
+    /// refuses to stop while dirty, snapshots its dirty flag
+    struct Committer {
+        dirty: bool,
+    }
+
+    impl Actor<bool, (), SomeError> for Committer {
+        async fn handler(&self, _input: bool, _ctx: &Context) -> Result<(), SomeError> {
+            Ok(())
+        }
+
+        async fn handler_mut(
+            &mut self,
+            dirty: bool,
+            _ctx: &Context,
+        ) -> Result<Option<()>, SomeError> {
+            self.dirty = dirty;
+
+            Ok(None)
+        }
+
+        async fn can_stop(&self) -> bool {
+            !self.dirty
+        }
+
+        fn snapshot(&self) -> Option<Vec<u8>> {
+            Some(vec![self.dirty as u8])
+        }
+    }
+
+    #[tokio::test]
+    async fn stop_refused() {
+        let handle = super::spawn(Committer { dirty: true }).await.unwrap();
+
+        let reply = handle.send(Message::Stop).await;
+        assert!(matches!(reply, Err(MsgError::StopRefused)));
+
+        let reply = handle.send(Message::Ping).await;
+        assert!(matches!(reply, Ok(Reply::Accepted)));
+
+        handle.send(Message::TaskMut(false)).await.unwrap();
+
+        let reply = handle.send(Message::Stop).await;
+        assert!(matches!(reply, Ok(Reply::Accepted)));
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        handle.send(Message::Ping).await.unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn snapshot() {
+        let handle = super::spawn(Committer { dirty: true }).await.unwrap();
+
+        let reply = handle.send(Message::Snapshot).await;
+        assert!(matches!(reply, Ok(Reply::Snapshot(bytes)) if bytes == [1]));
+
+        let handle = super::spawn(Mult { a: 3 }).await.unwrap();
+
+        let reply = handle.send(Message::Snapshot).await;
+        assert!(matches!(reply, Err(MsgError::NotSupported)));
+    }
+
+    #[tokio::test]
+    async fn streaming() {
+        let handle = super::spawn(Chunker).await.unwrap();
+
+        let (writer, mut chunks) = ReplyWriter::channel(1);
+        let (reply, received) = tokio::join!(handle.send_streaming(3, writer), async {
+            let mut received = vec![];
+            while let Some(chunk) = chunks.recv().await {
+                received.push(chunk);
+            }
+
+            received
+        });
+
+        assert!(matches!(reply, Ok(Reply::Accepted)));
+        assert_eq!(received, vec![vec![0; 4], vec![1; 4], vec![2; 4]]);
+
+        let reply = handle.send(Message::Stream(3)).await;
+        assert!(matches!(reply, Err(MsgError::NotAllowed)));
+
+        let handle = super::spawn(Mult { a: 3 }).await.unwrap();
+
+        let (writer, _chunks) = ReplyWriter::channel(1);
+        let reply = handle.send_streaming(3, writer).await;
+        assert!(matches!(reply, Err(MsgError::NotSupported)));
+    }
+
+    /// counts its calls in a shared context
+    struct Tally;
+
+    impl Actor<(), usize, SomeError, Arc<AtomicUsize>> for Tally {
+        async fn handler(
+            &self,
+            _input: (),
+            ctx: &Context<Arc<AtomicUsize>>,
+        ) -> Result<usize, SomeError> {
+            Ok(ctx.shared().fetch_add(1, Ordering::SeqCst) + 1)
+        }
+    }
+
+    #[tokio::test]
+    async fn shared_context() {
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let a = super::spawn_with_context(Tally, calls.clone())
+            .await
+            .unwrap();
+        let b = super::spawn_with_context(Tally, calls.clone())
+            .await
+            .unwrap();
+
+        a.send(Message::Task(())).await.unwrap();
+        let reply = b.send(Message::Task(())).await.unwrap();
+
+        assert!(matches!(reply, Reply::Task(2)));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn concurrent() {
+        let handle = super::spawn_concurrent(Sleeper, 2).await.unwrap();
+
+        let start = Instant::now();
+        let (a, b) = tokio::join!(
+            handle.send(Message::Task(200)),
+            handle.send(Message::Task(200))
+        );
+
+        assert!(matches!(a, Ok(Reply::Task(200))));
+        assert!(matches!(b, Ok(Reply::Task(200))));
+        assert!(start.elapsed() < Duration::from_millis(350));
+    }
+
+    #[tokio::test]
+    async fn concurrent_barrier() {
+        let handle = super::spawn_concurrent(Counter { count: 0 }, 4)
+            .await
+            .unwrap();
+
+        handle.send(Message::Task(0)).await.unwrap();
+        handle.notify(Message::TaskMut(3)).await.unwrap();
+
+        let reply = handle.send(Message::Task(0)).await.unwrap();
+        assert!(matches!(reply, Reply::Task(3)));
+
+        handle.send(Message::Stop).await.unwrap();
+        assert!(handle.send(Message::Ping).await.is_err());
+    }
+
+    /// spins until its context is cancelled, reporting whether it was
+    struct Patient {
+        gave_up: Arc<AtomicUsize>,
+    }
+
+    impl Actor<(), (), SomeError> for Patient {
+        async fn handler(&self, _input: (), ctx: &Context) -> Result<(), SomeError> {
+            let start = Instant::now();
+            while start.elapsed() < Duration::from_secs(5) {
+                if ctx.cancellation_token().is_cancelled() {
+                    self.gave_up.fetch_add(1, Ordering::SeqCst);
+                    break;
+                }
+
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn cancellation() {
+        let gave_up = Arc::new(AtomicUsize::new(0));
+
+        let sequential = super::spawn(Patient {
+            gave_up: gave_up.clone(),
+        })
+        .await
+        .unwrap();
+        let concurrent = super::spawn_concurrent(
+            Patient {
+                gave_up: gave_up.clone(),
+            },
+            2,
+        )
+        .await
+        .unwrap();
+
+        for handle in [&sequential, &concurrent] {
+            let sent = handle.send(Message::Task(()));
+            assert!(
+                tokio::time::timeout(Duration::from_millis(50), sent)
+                    .await
+                    .is_err()
+            );
+        }
+
+        // both handlers notice the callers are gone well before running out
+        let start = Instant::now();
+        while gave_up.load(Ordering::SeqCst) < 2 {
+            assert!(start.elapsed() < Duration::from_secs(1));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let reply = sequential.send(Message::Ping).await;
+        assert!(matches!(reply, Ok(Reply::Accepted)));
+    }
+
+    /// reports whether its context was cancelled while it slept
+    struct Reluctant {
+        stoppable: bool,
+    }
+
+    impl Actor<(), bool, SomeError> for Reluctant {
+        async fn handler(&self, _input: (), ctx: &Context) -> Result<bool, SomeError> {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+
+            Ok(ctx.cancellation_token().is_cancelled())
+        }
+
+        async fn can_stop(&self) -> bool {
+            self.stoppable
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_stop() {
+        for stoppable in [false, true] {
+            let handle = super::spawn_concurrent(Reluctant { stoppable }, 2)
+                .await
+                .unwrap();
+
+            let (task, stop) = tokio::join!(handle.send(Message::Task(())), async {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                handle.send(Message::Stop).await
+            });
+
+            // only a stop that goes through cancels handlers in flight
+            assert!(matches!(task, Ok(Reply::Task(cancelled)) if cancelled == stoppable));
+            match stoppable {
+                true => assert!(matches!(stop, Ok(Reply::Accepted))),
+                false => assert!(matches!(stop, Err(MsgError::StopRefused))),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn dropped_count() {
+        let handle = super::spawn_with_capacity(Sleeper, 2).await.unwrap();
+
+        // keep the actor busy so nothing leaves the queue
+        handle.notify(Message::Task(300)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let rejected = (0..10)
+            .filter(|_| {
+                matches!(
+                    handle.clone().try_send(Message::Task(0)),
+                    Err(MsgError::Busy)
+                )
+            })
+            .count();
+
+        assert_eq!(rejected, 8);
+        assert_eq!(handle.dropped_count(), 8);
+    }
+
+    #[cfg(all(feature = "tokio-console", feature = "remote"))]
+    #[tokio::test]
+    async fn named_tasks() {
+        use crate::actors::remote::{
+            self,
+            dencoder::{Dencoder, bitcode::BitcodeDencoder},
+        };
+        use crate::messaging::MsgResult;
+
+        let handle = super::spawn(Mult { a: 3 }).await.unwrap();
+
+        let reply = handle.send(Message::Task(5)).await.unwrap();
+        assert!(matches!(reply, Reply::Task(15)));
+
+        let (_, untyped) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+
+        let msg = BitcodeDencoder::encode(Message::Task(7u32)).unwrap();
+        let raw = untyped.send(msg).await.unwrap();
+        let reply = BitcodeDencoder::decode::<MsgResult<u32, SomeError>>(raw)
+            .unwrap()
+            .unwrap();
+        assert!(matches!(reply, Reply::Task(21)));
+    }
 }
@@ -2,13 +2,23 @@
 //! support for remote access to local actors
 //!
 
-use std::fmt::Display;
+use std::{fmt::Display, marker::PhantomData, sync::Arc, time::Duration};
 
+use address::ActorAddress;
 use dencoder::Dencoder;
+use futures::future;
+use netlayer::NetLayer;
+use router::{RemoteHandle, Router, RouterHandle, RouterOpts};
 use serde::{Serialize, de::DeserializeOwned};
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{
+    mpsc::{self, error::SendTimeoutError},
+    oneshot,
+};
 
-use crate::messaging::{Message, MsgError, MsgResult};
+use crate::{
+    messaging::{Message, MsgError, MsgResult, Reply, ReplyWriter, TypeSignature},
+    utils::spawn_task,
+};
 
 use super::{
     Actor,
@@ -20,6 +30,11 @@ pub mod dencoder;
 pub mod netlayer;
 pub mod router;
 
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// how long [`UntypedHandle::send()`] waits for room in a saturated actor's queue
+const BUSY_WAIT: Duration = Duration::from_millis(100);
+
 ///
 /// spawn an actor, wrapping it behind an untyped handle.
 ///
@@ -34,16 +49,100 @@ where
     E: Clone + Send + Serialize + 'static,
     D: Dencoder,
 {
-    let local_handle = local::spawn(actor).await.map_err(Error::Local)?;
+    spawn_untyped_with_context::<I, O, E, (), D>(actor, ()).await
+}
+
+///
+/// like [`spawn_untyped()`], but queueing at most `capacity` messages before reporting the actor as busy.
+///
+/// a capacity of 0 is raised to 1.
+///
+pub async fn spawn_untyped_with_capacity<I, O, E, D>(
+    actor: impl Actor<I, O, E> + Send + 'static,
+    capacity: usize,
+) -> Result<(LocalHandle<I, O, E>, UntypedHandle), Error>
+where
+    I: Clone + Send + DeserializeOwned + 'static,
+    O: Clone + Send + Serialize + 'static,
+    E: Clone + Send + Serialize + 'static,
+    D: Dencoder,
+{
+    untyped::<I, O, E, (), D>(actor, (), capacity).await
+}
+
+///
+/// like [`spawn_untyped()`], for an actor shared with its spawner through an [`Arc`].
+///
+/// handlers take `&self`, so the actor can keep referencing state the spawner still holds
+/// instead of deep-cloning it. shared actors can't be mutated, so `TaskMut` is refused with
+/// [`MsgError::NotSupported`], even through a handle allowing it.
+///
+pub async fn spawn_untyped_shared<I, O, E, D>(
+    actor: Arc<impl Actor<I, O, E> + Send + Sync + 'static>,
+) -> Result<(LocalHandle<I, O, E>, UntypedHandle), Error>
+where
+    I: Clone + Send + DeserializeOwned + 'static,
+    O: Clone + Send + Serialize + 'static,
+    E: Clone + Send + Serialize + 'static,
+    D: Dencoder,
+{
+    spawn_untyped::<I, O, E, D>(actor).await
+}
+
+///
+/// spawn an actor sharing `ctx` with its handlers, wrapping it behind an untyped handle.
+///
+/// see [`spawn_untyped()`].
+///
+pub async fn spawn_untyped_with_context<I, O, E, C, D>(
+    actor: impl Actor<I, O, E, C> + Send + 'static,
+    ctx: C,
+) -> Result<(LocalHandle<I, O, E>, UntypedHandle), Error>
+where
+    I: Clone + Send + DeserializeOwned + 'static,
+    O: Clone + Send + Serialize + 'static,
+    E: Clone + Send + Serialize + 'static,
+    C: Send + Sync + 'static,
+    D: Dencoder,
+{
+    untyped::<I, O, E, C, D>(actor, ctx, DEFAULT_CAPACITY).await
+}
+
+async fn untyped<I, O, E, C, D>(
+    actor: impl Actor<I, O, E, C> + Send + 'static,
+    ctx: C,
+    capacity: usize,
+) -> Result<(LocalHandle<I, O, E>, UntypedHandle), Error>
+where
+    I: Clone + Send + DeserializeOwned + 'static,
+    O: Clone + Send + Serialize + 'static,
+    E: Clone + Send + Serialize + 'static,
+    C: Send + Sync + 'static,
+    D: Dencoder,
+{
+    let busy: MsgResult<O, E> = Err(MsgError::Busy);
+    let busy_reply = D::encode(busy)
+        .inspect_err(|err| tracing::warn!("untyped: failed to encode busy reply: {err}"))
+        .ok();
+
+    let local_handle = local::spawn_with_context(actor, ctx)
+        .await
+        .map_err(Error::Local)?;
     let inner_handle = local_handle.clone();
-    let (sender, mut receiver) =
-        mpsc::channel::<(Vec<u8>, HandleOpts, oneshot::Sender<Result<Vec<u8>, Error>>)>(1024);
+    let (sender, mut receiver) = mpsc::channel::<UntypedEnvelope>(capacity.max(1));
     let (conf_sender, conf_receiver) = oneshot::channel::<Result<(), Error>>();
+    let signature = TypeSignature::of::<I, O, E>();
 
-    tokio::spawn(async move {
+    spawn_task("myriam::untyped", async move {
         let _ = conf_sender.send(Ok(()));
-        while let Some((msg, opts, sender)) = receiver.recv().await {
+        while let Some((msg, opts, sender, writer)) = receiver.recv().await {
             match D::decode::<Message<I>>(msg) {
+                Ok(Message::Describe) => {
+                    // answered from the types we were spawned with, without reaching the actor
+                    let res: MsgResult<O, E> = Ok(Reply::Describe(signature.clone()));
+                    let res = D::encode(res).map_err(|e| Error::Encode(e.to_string()));
+                    let _ = sender.send(res);
+                }
                 Ok(msg) => {
                     if let Err(err) = opts.validate::<I, E>(&msg) {
                         let err: MsgResult<O, E> = Err(err);
@@ -52,18 +151,32 @@ where
                         continue;
                     }
 
+                    if let Message::Tell(input) = msg {
+                        // ack as soon as it's queued, the handler runs on its own time
+                        let res: MsgResult<O, E> = inner_handle
+                            .notify(Message::Tell(input))
+                            .await
+                            .map(|_| Reply::Accepted);
+                        let res = D::encode(res).map_err(|e| Error::Encode(e.to_string()));
+                        let _ = sender.send(res);
+                        continue;
+                    }
+
                     let stop_msg = matches!(msg, Message::<I>::Stop);
 
-                    let res = inner_handle.send(msg).await;
+                    let res = match (msg, writer) {
+                        (Message::Stream(input), Some(writer)) => {
+                            inner_handle.send_streaming(input, writer).await
+                        }
+                        (msg, _) => inner_handle.send(msg).await,
+                    };
+                    let stopped = stop_msg && !matches!(res, Err(MsgError::StopRefused));
+
                     match D::encode(res).map_err(|e| Error::Encode(e.to_string())) {
                         Ok(enc) => {
                             if sender.send(Ok(enc)).is_err() {
                                 tracing::warn!("untyped: failed to send reply");
                             }
-
-                            if stop_msg {
-                                break;
-                            }
                         }
                         Err(err) => {
                             tracing::error!("untyped: failed to encode reply");
@@ -72,6 +185,11 @@ where
                             });
                         }
                     }
+
+                    // the stop went through; replying was best-effort
+                    if stopped {
+                        break;
+                    }
                 }
                 Err(err) => {
                     tracing::error!("untyped: failed to decode incoming message: {err}");
@@ -83,6 +201,12 @@ where
                 }
             }
         }
+
+        // refuse new messages and let whoever is still queued know we're gone
+        receiver.close();
+        while let Some((_, _, sender, _)) = receiver.recv().await {
+            let _ = sender.send(Err(Error::Stopped));
+        }
     });
 
     conf_receiver
@@ -94,10 +218,153 @@ where
         UntypedHandle {
             sender,
             opts: HandleOpts::new(),
+            busy_reply: Arc::new(busy_reply),
+            label: None,
         },
     ))
 }
 
+///
+/// spawn an actor behind a new router using this net layer, and attach it.
+///
+/// shorthand for [`spawn_untyped()`], [`Router::with_netlayer()`] and [`RouterHandle::attach()`]
+/// for services exposing a single actor.
+///
+pub async fn serve<I, O, E, D, N>(
+    actor: impl Actor<I, O, E> + Send + 'static,
+    netlayer: N,
+    opts: Option<RouterOpts>,
+) -> Result<(LocalHandle<I, O, E>, RouterHandle, ActorAddress), Error>
+where
+    I: Clone + Send + DeserializeOwned + 'static,
+    O: Clone + Send + Serialize + 'static,
+    E: Clone + Send + Serialize + 'static,
+    D: Dencoder,
+    N: NetLayer + Send + 'static,
+    <N as NetLayer>::Error: Send + Display,
+{
+    let (local_handle, untyped_handle) = spawn_untyped::<I, O, E, D>(actor).await?;
+
+    let router = Router::with_netlayer(netlayer, opts)
+        .await
+        .map_err(Error::Router)?;
+
+    let address = router.attach(untyped_handle).await.map_err(Error::Router)?;
+
+    Ok((local_handle, router, address))
+}
+
+///
+/// send the same message to all of these handles, returning the first successful reply.
+///
+/// the remaining requests are dropped as soon as one succeeds. if every request fails, the last error is returned.
+///
+pub async fn race<I, O, E, D, N>(
+    handles: &[RemoteHandle<I, O, E, D, N>],
+    msg: Message<I>,
+) -> Result<MsgResult<O, E>, router::Error>
+where
+    I: Clone + Serialize + DeserializeOwned,
+    O: Serialize + DeserializeOwned,
+    E: Serialize + DeserializeOwned,
+    D: Dencoder,
+    N: NetLayer,
+    <N as NetLayer>::Error: Display,
+{
+    if handles.is_empty() {
+        return Err(router::Error::Send("no handles to race".into()));
+    }
+
+    let requests = handles
+        .iter()
+        .map(|handle| Box::pin(handle.send(msg.clone())));
+
+    let (res, _) = future::select_ok(requests).await?;
+
+    Ok(res)
+}
+
+///
+/// send an already encoded message to `address`, returning the encoded reply.
+///
+/// the net layer is picked from the address' protocol among those compiled in, and only lives
+/// for this request. Tor clients have to bootstrap first, so prefer a [`RemoteHandle`] when
+/// sending more than once.
+///
+#[cfg_attr(not(any(feature = "tcp", feature = "tor")), allow(unused_variables))]
+pub async fn send_to(
+    address: &ActorAddress,
+    msg: Vec<u8>,
+    opts: SendOpts,
+) -> Result<Vec<u8>, Error> {
+    let send = async {
+        match address.proto_id() {
+            #[cfg(feature = "tcp")]
+            proto if proto == netlayer::tcp_layer::TcpNetLayer::name() => {
+                let netlayer = netlayer::tcp_layer::TcpNetLayer::new();
+                router::send_bytes(address, netlayer, &msg).await
+            }
+            #[cfg(feature = "tor")]
+            proto if proto == netlayer::tor_layer::TorLayer::name() => {
+                let netlayer =
+                    netlayer::tor_layer::TorLayer::new_for_client(opts.tor_nickname.clone())
+                        .await
+                        .map_err(|e| router::Error::Connect(e.to_string()))?;
+                router::send_bytes(address, netlayer, &msg).await
+            }
+            proto => Err(router::Error::Connect(format!(
+                "no net layer compiled in for protocol {proto}"
+            ))),
+        }
+    };
+
+    match opts.timeout {
+        Some(timeout) => tokio::time::timeout(timeout, send)
+            .await
+            .map_err(|_| Error::Send("timed out".into()))?,
+        None => send.await,
+    }
+    .map_err(Error::Router)
+}
+
+///
+/// options for [`send_to()`]
+///
+#[derive(Debug, Clone)]
+pub struct SendOpts {
+    timeout: Option<Duration>,
+    #[cfg_attr(not(feature = "tor"), allow(dead_code))]
+    tor_nickname: String,
+}
+
+impl SendOpts {
+    /// no timeout, default Tor client nickname
+    pub fn new() -> Self {
+        Self {
+            timeout: None,
+            tor_nickname: "myriam-send-to".into(),
+        }
+    }
+
+    /// give up on the whole request, connecting included, after `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// nickname of the Tor client bootstrapped for `tor` addresses
+    pub fn with_tor_nickname(mut self, nickname: impl Into<String>) -> Self {
+        self.tor_nickname = nickname.into();
+        self
+    }
+}
+
+impl Default for SendOpts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 ///
 /// options for this handle
 ///
@@ -149,30 +416,65 @@ impl Default for HandleOpts {
     }
 }
 
+/// encoded message, handle options, where to reply and where to stream chunks (if streaming)
+type UntypedEnvelope = (
+    Vec<u8>,
+    HandleOpts,
+    oneshot::Sender<Result<Vec<u8>, Error>>,
+    Option<ReplyWriter>,
+);
+
 ///
 /// untyped handle for remote messaging, when types aren't available.
 ///
 #[derive(Debug, Clone)]
-#[allow(clippy::type_complexity)]
 pub struct UntypedHandle {
-    sender: mpsc::Sender<(Vec<u8>, HandleOpts, oneshot::Sender<Result<Vec<u8>, Error>>)>,
+    sender: mpsc::Sender<UntypedEnvelope>,
     opts: HandleOpts,
+    busy_reply: Arc<Option<Vec<u8>>>,
+    label: Option<Arc<str>>,
 }
 
 impl UntypedHandle {
     ///
     /// attempt to message this actor with an encoded message, getting an encoded response in return.
     ///
+    /// fails with [`Error::Busy`] if the actor's queue stays full for a short while.
+    ///
     pub async fn send(&self, msg: Vec<u8>) -> Result<Vec<u8>, Error> {
+        self.relay(msg, None).await
+    }
+
+    ///
+    /// like [`Self::send()`], but encoded [`Message::Stream`]s get their chunks written to `writer`.
+    ///
+    /// resolves once the handler is done, so chunks should be read concurrently.
+    ///
+    pub async fn send_streaming(
+        &self,
+        msg: Vec<u8>,
+        writer: ReplyWriter,
+    ) -> Result<Vec<u8>, Error> {
+        self.relay(msg, Some(writer)).await
+    }
+
+    async fn relay(&self, msg: Vec<u8>, writer: Option<ReplyWriter>) -> Result<Vec<u8>, Error> {
         let (sender, receiver) = oneshot::channel();
 
         self.sender
-            .send((msg, self.opts.clone(), sender))
+            .send_timeout((msg, self.opts.clone(), sender, writer), BUSY_WAIT)
             .await
-            .map_err(|e| {
-                tracing::error!("untyped send: {e}");
+            .map_err(|e| match e {
+                SendTimeoutError::Timeout(_) => {
+                    tracing::warn!("untyped send: actor busy");
 
-                Error::Send(e.to_string())
+                    Error::Busy
+                }
+                SendTimeoutError::Closed(_) => {
+                    tracing::error!("untyped send: {e}");
+
+                    Error::Send(e.to_string())
+                }
             })?;
 
         receiver.await.map_err(|e| {
@@ -198,6 +500,99 @@ impl UntypedHandle {
     pub fn allow_stop(&mut self, allow: bool) {
         self.opts.allow_stop = allow;
     }
+
+    ///
+    /// label this handle, e.g. for telling apart actors sharing a router in its logs and metrics.
+    ///
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into().into());
+        self
+    }
+
+    /// label given with [`Self::with_label()`], if any
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    ///
+    /// encoded [`MsgError::Busy`] reply, for telling remote clients to try again later.
+    ///
+    /// none if it couldn't be encoded at spawn.
+    ///
+    pub fn busy_reply(&self) -> Option<&[u8]> {
+        self.busy_reply.as_deref()
+    }
+}
+
+///
+/// typed façade over an [`UntypedHandle`], encoding and decoding through `D`.
+///
+/// types aren't checked against the actor's; mismatches surface as decoding errors.
+///
+#[derive(Debug)]
+pub struct TypedHandle<I, O, E, D: Dencoder> {
+    handle: UntypedHandle,
+
+    _ipd: PhantomData<I>,
+    _opd: PhantomData<O>,
+    _epd: PhantomData<E>,
+    _dpd: PhantomData<D>,
+}
+
+impl<I, O, E, D: Dencoder> Clone for TypedHandle<I, O, E, D> {
+    fn clone(&self) -> Self {
+        Self {
+            handle: self.handle.clone(),
+            _ipd: PhantomData,
+            _opd: PhantomData,
+            _epd: PhantomData,
+            _dpd: PhantomData,
+        }
+    }
+}
+
+impl<I, O, E, D> TypedHandle<I, O, E, D>
+where
+    I: Serialize,
+    O: DeserializeOwned,
+    E: DeserializeOwned,
+    D: Dencoder,
+{
+    /// wrap this untyped handle
+    pub fn new(handle: UntypedHandle) -> Self {
+        Self {
+            handle,
+            _ipd: PhantomData,
+            _opd: PhantomData,
+            _epd: PhantomData,
+            _dpd: PhantomData,
+        }
+    }
+
+    ///
+    /// attempt to send a message to this actor
+    ///
+    /// failures to encode or relay the message, or for the actor to decode it, come back as
+    /// [`MsgError::Send`]. failures to encode or decode the reply come back as [`MsgError::Recv`].
+    ///
+    pub async fn send(&self, msg: Message<I>) -> MsgResult<O, E> {
+        let msg = D::encode(msg).map_err(|e| MsgError::Send(e.to_string()))?;
+
+        let res = self.handle.send(msg).await.map_err(|e| match e {
+            Error::Busy => MsgError::Busy,
+            // the actor couldn't make sense of what we sent
+            Error::Decode(ctx) => MsgError::Send(ctx),
+            Error::Recv(ctx) | Error::Encode(ctx) => MsgError::Recv(ctx),
+            e => MsgError::Send(e.to_string()),
+        })?;
+
+        D::decode::<MsgResult<O, E>>(res).map_err(|e| MsgError::Recv(e.to_string()))?
+    }
+
+    /// wrapped untyped handle
+    pub fn untyped(&self) -> &UntypedHandle {
+        &self.handle
+    }
 }
 
 ///
@@ -223,6 +618,15 @@ pub enum Error {
 
     // #[error("failed to encode message")]
     Encode(String),
+
+    // #[error("actor stopped")]
+    Stopped,
+
+    // #[error("actor busy")]
+    Busy,
+
+    // #[error("router failed")]
+    Router(router::Error),
 }
 
 impl Display for Error {
@@ -234,24 +638,110 @@ impl Display for Error {
             Error::Recv(ctx) => write!(f, "failed to receive message: {ctx}"),
             Error::Decode(ctx) => write!(f, "failed to decode message: {ctx}"),
             Error::Encode(ctx) => write!(f, "failed to encode message: {ctx}"),
+            Error::Stopped => write!(f, "actor stopped"),
+            Error::Busy => write!(f, "actor busy"),
+            Error::Router(ctx) => write!(f, "router failed: {ctx}"),
         }
     }
 }
 
 impl std::error::Error for Error {}
 
-#[cfg(test)]
+#[cfg(all(test, feature = "tcp"))]
 mod tests {
-    use std::time::Duration;
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicU32, Ordering},
+        },
+        time::Duration,
+    };
 
     use crate::{
         actors::{
-            remote::dencoder::{Dencoder, bitcode::BitcodeDencoder},
+            Actor, Context,
+            remote::{
+                Error, SendOpts,
+                address::ActorAddress,
+                dencoder::{self, Dencoder, bitcode::BitcodeDencoder},
+                netlayer::tcp_layer::TcpNetLayer,
+                router::{self, RemoteHandle, Router, RouterOpts},
+            },
             tests::*,
         },
         messaging::{Message, MsgError, MsgResult, Reply},
     };
 
+    /// reports a level set from outside
+    struct Gauge {
+        level: AtomicU32,
+    }
+
+    impl Actor<(), u32, SomeError> for Gauge {
+        async fn handler(&self, _input: (), _ctx: &Context) -> Result<u32, SomeError> {
+            Ok(self.level.load(Ordering::SeqCst))
+        }
+    }
+
+    #[tokio::test]
+    async fn shared_actor() {
+        let gauge = Arc::new(Gauge {
+            level: AtomicU32::new(1),
+        });
+
+        let (_, mut handle) =
+            super::spawn_untyped_shared::<_, _, _, BitcodeDencoder>(gauge.clone())
+                .await
+                .unwrap();
+        handle.allow_mut(true);
+        let handle = super::TypedHandle::<(), u32, SomeError, BitcodeDencoder>::new(handle);
+
+        let res = handle.send(Message::Task(())).await;
+        assert!(matches!(res, Ok(Reply::Task(1))));
+
+        gauge.level.store(7, Ordering::SeqCst);
+
+        let res = handle.send(Message::Task(())).await;
+        assert!(matches!(res, Ok(Reply::Task(7))));
+        assert_eq!(Arc::strong_count(&gauge), 2);
+
+        let res = handle.send(Message::TaskMut(())).await;
+        assert!(matches!(res, Err(MsgError::NotSupported)));
+    }
+
+    #[tokio::test]
+    async fn typed_handle() {
+        let (_, handle) = super::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 4 })
+            .await
+            .unwrap();
+
+        let typed = super::TypedHandle::<u32, u32, SomeError, BitcodeDencoder>::new(handle);
+
+        let res = typed.send(Message::Task(5)).await;
+        assert!(matches!(res, Ok(Reply::Task(20))));
+
+        let res = typed.send(Message::TaskMut(5)).await;
+        assert!(matches!(res, Err(MsgError::NotAllowed)));
+
+        // a request the actor can't decode is the sender's fault
+        let mismatched =
+            super::TypedHandle::<(), u32, SomeError, BitcodeDencoder>::new(typed.untyped().clone());
+        let res = mismatched.send(Message::Task(())).await;
+        assert!(matches!(res, Err(MsgError::Send(_))));
+    }
+
+    #[tokio::test]
+    async fn zero_capacity() {
+        let (_, handle) =
+            super::spawn_untyped_with_capacity::<_, _, _, BitcodeDencoder>(Mult { a: 2 }, 0)
+                .await
+                .unwrap();
+        let typed = super::TypedHandle::<u32, u32, SomeError, BitcodeDencoder>::new(handle);
+
+        let res = typed.send(Message::Task(3)).await;
+        assert!(matches!(res, Ok(Reply::Task(6))));
+    }
+
     #[tokio::test]
     async fn spawning_and_messaging() {
         let mult = Mult { a: 2 };
@@ -331,4 +821,190 @@ mod tests {
 
         assert!(matches!(res, MsgError::NotAllowed));
     }
+
+    #[tokio::test]
+    async fn describe() {
+        let mult = Mult { a: 2 };
+
+        let (_, handle) = super::spawn_untyped::<_, _, _, BitcodeDencoder>(mult)
+            .await
+            .unwrap();
+
+        let msg = BitcodeDencoder::encode(Message::<u32>::Describe).unwrap();
+
+        let raw = handle.send(msg).await.unwrap();
+        let res = BitcodeDencoder::decode::<MsgResult<u32, SomeError>>(raw)
+            .unwrap()
+            .unwrap();
+
+        match res {
+            Reply::Describe(signature) => {
+                assert!(signature.input().contains("u32"));
+                assert!(signature.output().contains("u32"));
+                assert!(signature.error().contains("SomeError"));
+            }
+            _ => panic!("expected Describe reply"),
+        }
+    }
+
+    #[tokio::test]
+    async fn stop_drains_queue() {
+        let (_, mut handle) = super::spawn_untyped::<_, _, _, BitcodeDencoder>(Sleeper)
+            .await
+            .unwrap();
+
+        handle.allow_stop(true);
+
+        let slow = {
+            let handle = handle.clone();
+            let msg = BitcodeDencoder::encode(Message::Task(100u64)).unwrap();
+            tokio::spawn(async move { handle.send(msg).await })
+        };
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let stop = {
+            let handle = handle.clone();
+            let msg = BitcodeDencoder::encode(Message::<u64>::Stop).unwrap();
+            tokio::spawn(async move { handle.send(msg).await })
+        };
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let msg = BitcodeDencoder::encode(Message::<u64>::Ping).unwrap();
+        let trailing = handle.send(msg).await;
+
+        assert!(slow.await.unwrap().is_ok());
+        assert!(stop.await.unwrap().is_ok());
+        assert!(matches!(trailing, Err(Error::Stopped)));
+    }
+
+    /// decodes like bitcode, fails every encode
+    struct NoEncode;
+
+    impl Dencoder for NoEncode {
+        fn encode<T: serde::Serialize>(_value: T) -> Result<Vec<u8>, dencoder::Error> {
+            Err(dencoder::Error::Encode("nope".into()))
+        }
+
+        fn decode<U: serde::de::DeserializeOwned>(value: Vec<u8>) -> Result<U, dencoder::Error> {
+            BitcodeDencoder::decode(value)
+        }
+    }
+
+    #[tokio::test]
+    async fn stop_despite_encode_failure() {
+        let mult = Mult { a: 2 };
+
+        let (_, mut handle) = super::spawn_untyped::<_, _, _, NoEncode>(mult)
+            .await
+            .unwrap();
+
+        handle.allow_stop(true);
+
+        let msg = BitcodeDencoder::encode(Message::<u32>::Stop).unwrap();
+        let res = handle.send(msg).await;
+        assert!(matches!(res, Err(Error::Encode(_))));
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let msg = BitcodeDencoder::encode(Message::<u32>::Ping).unwrap();
+        let res = handle.send(msg).await;
+        assert!(matches!(res, Err(Error::Send(_) | Error::Stopped)));
+    }
+
+    #[tokio::test]
+    async fn race() {
+        let (_, handle) = super::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let fast = router.attach(handle).await.unwrap();
+
+        // accepts connections but never replies
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let stalled =
+            ActorAddress::new::<TcpNetLayer>(&listener.local_addr().unwrap().to_string()).unwrap();
+        tokio::spawn(async move {
+            let mut streams = vec![];
+            while let Ok((stream, _)) = listener.accept().await {
+                streams.push(stream);
+            }
+        });
+
+        let refused = {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            ActorAddress::new::<TcpNetLayer>(&listener.local_addr().unwrap().to_string()).unwrap()
+        };
+
+        let handles = [stalled, refused, fast]
+            .iter()
+            .map(|addr| {
+                RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+                    addr,
+                    TcpNetLayer::new(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let res = tokio::time::timeout(
+            Duration::from_secs(5),
+            super::race(&handles, Message::Task(5)),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(matches!(res, Ok(Reply::Task(15))));
+
+        let res = super::race(&handles[1..2], Message::Task(5)).await;
+        assert!(matches!(res, Err(router::Error::WithContext { .. })));
+    }
+
+    #[tokio::test]
+    async fn serve() {
+        let (_, _router, addr) = super::serve::<_, _, _, BitcodeDencoder, _>(
+            Mult { a: 3 },
+            TcpNetLayer::new(),
+            Some(RouterOpts::default()),
+        )
+        .await
+        .unwrap();
+
+        let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        let res = remote.send(Message::Task(5)).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Task(15))));
+    }
+
+    #[tokio::test]
+    async fn send_to() {
+        let (_, _router, addr) = super::serve::<_, _, _, BitcodeDencoder, _>(
+            Mult { a: 3 },
+            TcpNetLayer::new(),
+            Some(RouterOpts::default()),
+        )
+        .await
+        .unwrap();
+
+        let addr = ActorAddress::try_parse(&addr.to_string()).unwrap();
+        let msg = BitcodeDencoder::encode(Message::Task(5u32)).unwrap();
+
+        let reply = super::send_to(&addr, msg, SendOpts::default())
+            .await
+            .unwrap();
+        let res: MsgResult<u32, SomeError> = BitcodeDencoder::decode(reply).unwrap();
+        assert!(matches!(res, Ok(Reply::Task(15))));
+
+        let unknown = addr.to_string().replacen("tcp", "carrier-pigeon", 1);
+        let unknown = ActorAddress::try_parse(&unknown).unwrap();
+        let res = super::send_to(&unknown, vec![], SendOpts::default()).await;
+        assert!(matches!(res, Err(Error::Router(router::Error::Connect(_)))));
+    }
 }
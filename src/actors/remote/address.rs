@@ -2,9 +2,9 @@
 //! remote address struct and utils
 //!
 
-use std::{fmt::Display, str::FromStr};
+use std::{fmt::Display, io::Write, path::Path, str::FromStr};
 
-use rand::Rng;
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
 
 use super::netlayer::NetLayer;
@@ -29,17 +29,23 @@ impl ActorAddress {
     where
         N: NetLayer,
     {
-        let proto_id = N::name();
-        let mut bytes = [0u8; 32];
-
-        let mut rng = rand::rng();
-        rng.fill(&mut bytes);
+        Self::new_with_rng::<N>(host, &mut rand::rng())
+    }
 
-        Ok(Self {
-            proto_id: proto_id.to_owned(),
-            host: host.to_owned(),
-            peer_id: PeerId::new()?,
-        })
+    ///
+    /// create a new address from this host and `NetLayer` parameter, drawing its
+    /// [`PeerId`] from the given RNG
+    ///
+    /// mostly useful for reproducible tests.
+    ///
+    pub fn new_with_rng<N>(host: &str, rng: &mut impl RngCore) -> Result<Self, Error>
+    where
+        N: NetLayer,
+    {
+        Ok(Self::new_with_peer_id::<N>(
+            host,
+            PeerId::new_with_rng(rng)?,
+        ))
     }
 
     ///
@@ -78,6 +84,45 @@ impl ActorAddress {
         })
     }
 
+    ///
+    /// copy of this address pointing at a different host
+    ///
+    pub fn with_host(&self, host: &str) -> Self {
+        Self {
+            proto_id: self.proto_id.clone(),
+            peer_id: self.peer_id.clone(),
+            host: host.to_owned(),
+        }
+    }
+
+    ///
+    /// check whether this address is meant for net layer `N`
+    ///
+    pub fn check_proto<N>(&self) -> Result<(), Error>
+    where
+        N: NetLayer,
+    {
+        if self.proto_id == N::name() {
+            Ok(())
+        } else {
+            Err(Error::Proto(format!(
+                "expected {}, got {}",
+                N::name(),
+                self.proto_id
+            )))
+        }
+    }
+
+    ///
+    /// check whether this address' host is well-formed for net layer `N`
+    ///
+    pub fn check_host<N>(&self) -> Result<(), Error>
+    where
+        N: NetLayer,
+    {
+        N::validate_host(&self.host).map_err(|err| Error::Host(err.to_string()))
+    }
+
     ///
     /// this actor's protocol ID
     ///
@@ -98,6 +143,41 @@ impl ActorAddress {
     pub fn host(&self) -> &str {
         &self.host
     }
+
+    ///
+    /// write this address to `path`, e.g. for handing the capability over to another process.
+    ///
+    /// the file is created or truncated, readable and writable only by its owner on Unix.
+    /// see [`Self::read_from_file()`].
+    ///
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        let mut file = options
+            .open(path.as_ref())
+            .map_err(|e| Error::Io(e.to_string()))?;
+
+        // the mode only applies to new files
+        #[cfg(unix)]
+        file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))
+            .map_err(|e| Error::Io(e.to_string()))?;
+
+        writeln!(file, "{self}").map_err(|e| Error::Io(e.to_string()))
+    }
+
+    ///
+    /// read an address written with [`Self::write_to_file()`] from `path`
+    ///
+    pub fn read_from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let contents =
+            std::fs::read_to_string(path.as_ref()).map_err(|e| Error::Io(e.to_string()))?;
+
+        Self::try_parse(contents.trim())
+    }
 }
 
 impl FromStr for ActorAddress {
@@ -132,16 +212,25 @@ impl Display for ActorAddress {
 /// wrapper over a bag of bytes, acting as a unique identifier
 /// inside a [`Router`]
 ///
-#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+/// peer IDs work as bearer tokens, so [`Debug`] only shows a short prefix (see [`Self::redacted()`]).
+/// [`Display`] shows the whole ID.
+///
+#[derive(Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct PeerId(Vec<u8>);
 
+/// base32 characters kept by [`PeerId::redacted()`]
+const REDACTED_PREFIX_LEN: usize = 6;
+
 impl PeerId {
     /// generate a new random PeerId
     pub fn new() -> Result<Self, Error> {
-        let mut rng = rand::rng();
+        Self::new_with_rng(&mut rand::rng())
+    }
 
+    /// generate a new PeerId drawn from the given RNG
+    pub fn new_with_rng(rng: &mut impl RngCore) -> Result<Self, Error> {
         let mut buffer = [0u8; 32];
-        rng.fill(&mut buffer);
+        rng.fill_bytes(&mut buffer);
 
         Ok(Self(buffer.to_vec()))
     }
@@ -177,6 +266,19 @@ impl PeerId {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    ///
+    /// displayable form of this PeerId showing only a short prefix, safe for logs
+    ///
+    pub fn redacted(&self) -> RedactedPeerId<'_> {
+        RedactedPeerId(self)
+    }
+}
+
+impl std::fmt::Debug for PeerId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PeerId({})", self.redacted())
+    }
 }
 
 impl Display for PeerId {
@@ -189,6 +291,23 @@ impl Display for PeerId {
     }
 }
 
+///
+/// [`PeerId`] displayed as a short prefix, see [`PeerId::redacted()`]
+///
+#[derive(Debug, Clone, Copy)]
+pub struct RedactedPeerId<'a>(&'a PeerId);
+
+impl Display for RedactedPeerId<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let full = self.0.to_string();
+
+        match full.get(..REDACTED_PREFIX_LEN) {
+            Some(prefix) if full.len() > REDACTED_PREFIX_LEN => write!(f, "{prefix}…"),
+            _ => write!(f, "…"),
+        }
+    }
+}
+
 ///
 /// Errors when creating a new address
 ///
@@ -197,6 +316,9 @@ impl Display for PeerId {
 pub enum Error {
     Malformed,
     Id,
+    Proto(String),
+    Host(String),
+    Io(String),
 }
 
 impl Display for Error {
@@ -204,17 +326,22 @@ impl Display for Error {
         match self {
             Error::Malformed => write!(f, "malformed actor address"),
             Error::Id => write!(f, "failed to generate peer ID"),
+            Error::Proto(ctx) => write!(f, "address protocol doesn't match net layer: {ctx}"),
+            Error::Host(ctx) => write!(f, "address host doesn't fit net layer: {ctx}"),
+            Error::Io(ctx) => write!(f, "failed to read or write address file: {ctx}"),
         }
     }
 }
 
 impl std::error::Error for Error {}
 
-#[cfg(test)]
+#[cfg(all(test, feature = "tcp"))]
 mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
     use crate::actors::remote::netlayer::tcp_layer::TcpNetLayer;
 
-    use super::ActorAddress;
+    use super::{ActorAddress, Error, PeerId};
 
     #[test]
     fn can_generate() {
@@ -226,6 +353,39 @@ mod tests {
         assert_eq!(52, addr.peer_id().to_string().len());
     }
 
+    #[test]
+    fn seeded_rng_is_reproducible() {
+        let a = PeerId::new_with_rng(&mut StdRng::seed_from_u64(42)).unwrap();
+        let b = PeerId::new_with_rng(&mut StdRng::seed_from_u64(42)).unwrap();
+
+        assert_eq!(a, b);
+
+        let addr_a =
+            ActorAddress::new_with_rng::<TcpNetLayer>("127.0.0.1", &mut StdRng::seed_from_u64(7))
+                .unwrap();
+        let addr_b =
+            ActorAddress::new_with_rng::<TcpNetLayer>("127.0.0.1", &mut StdRng::seed_from_u64(7))
+                .unwrap();
+
+        assert_eq!(addr_a.to_string(), addr_b.to_string());
+    }
+
+    #[test]
+    fn redacted() {
+        let id = PeerId::new_with_rng(&mut StdRng::seed_from_u64(42)).unwrap();
+        let full = id.to_string();
+
+        let redacted = id.redacted().to_string();
+        assert_eq!(redacted, format!("{}…", &full[..6]));
+        assert!(!redacted.contains(&full));
+
+        let debug = format!("{id:?}");
+        assert!(debug.contains(&redacted));
+        assert!(!debug.contains(&full));
+
+        assert_eq!(PeerId::new_from_bytes(b"ab").redacted().to_string(), "…");
+    }
+
     #[test]
     fn can_parse() {
         let addr_str = "tcp:somethingelse@example.com";
@@ -248,6 +408,16 @@ mod tests {
         assert_eq!("example.com:8037", addr.host());
     }
 
+    #[test]
+    fn with_host() {
+        let addr = ActorAddress::try_parse("tcp:anotherone@0.0.0.0:8037").unwrap();
+        let moved = addr.with_host("example.com:9000");
+
+        assert_eq!(addr.proto_id(), moved.proto_id());
+        assert_eq!(addr.peer_id(), moved.peer_id());
+        assert_eq!("example.com:9000", moved.host());
+    }
+
     #[test]
     fn empty_address_fails() {
         ActorAddress::try_parse(":@").unwrap_err();
@@ -257,4 +427,32 @@ mod tests {
     fn malformed_address_fails() {
         ActorAddress::try_parse("jkfd@fdk:asdj").unwrap_err();
     }
+
+    #[test]
+    fn file_round_trip() {
+        let addr = ActorAddress::new::<TcpNetLayer>("127.0.0.1:8080").unwrap();
+        let path = std::env::temp_dir().join(format!("myriam-addr-{}", addr.peer_id()));
+
+        addr.write_to_file(&path).unwrap();
+        let read = ActorAddress::read_from_file(&path);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        std::fs::write(&path, "not an address").unwrap();
+        let garbage = ActorAddress::read_from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(read.unwrap().to_string(), addr.to_string());
+        assert!(matches!(garbage, Err(Error::Malformed)));
+        assert!(matches!(
+            ActorAddress::read_from_file(&path),
+            Err(Error::Io(_))
+        ));
+    }
 }
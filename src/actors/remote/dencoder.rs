@@ -46,3 +46,29 @@ impl Display for Error {
 }
 
 impl std::error::Error for Error {}
+
+///
+/// prefix encoded bytes with the version of the format they're in
+///
+pub(crate) fn with_format(format: u8, bytes: Vec<u8>) -> Vec<u8> {
+    let mut prefixed = Vec::with_capacity(bytes.len() + 1);
+    prefixed.push(format);
+    prefixed.extend_from_slice(&bytes);
+
+    prefixed
+}
+
+///
+/// bytes following the format version prefix, if it's the expected one.
+///
+/// a peer encoding in another format fails here instead of decoding into garbage.
+///
+pub(crate) fn strip_format(format: u8, bytes: &[u8]) -> Result<&[u8], Error> {
+    match bytes.split_first() {
+        Some((&found, rest)) if found == format => Ok(rest),
+        Some((&found, _)) => Err(Error::Decode(format!(
+            "format version mismatch (expected {format}, got {found})"
+        ))),
+        None => Err(Error::Decode("missing format version".into())),
+    }
+}
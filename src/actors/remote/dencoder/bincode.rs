@@ -2,12 +2,22 @@
 //! Bincode-based Dencoder impl
 //!
 
+use bincode::Options;
 use serde::{Serialize, de::DeserializeOwned};
 
-use super::Dencoder;
+use super::{Dencoder, strip_format, with_format};
 
+/// format version prefixed to [`BincodeDencoder`] output
+pub const FIXINT_FORMAT: u8 = 1;
+
+/// format version prefixed to [`BincodeVarintDencoder`] output
+pub const VARINT_FORMAT: u8 = 2;
+
+///
+/// Dencoder implemented over Bincode, with its default (fixed-size integer) encoding
 ///
-/// Dencoder implemented over Bincode
+/// not wire-compatible with [`BincodeVarintDencoder`]; both ends must use the same one.
+/// output is prefixed with [`FIXINT_FORMAT`], so decoding anything else fails.
 ///
 #[derive(Debug)]
 #[deprecated(
@@ -19,11 +29,44 @@ pub struct BincodeDencoder;
 #[allow(deprecated)]
 impl Dencoder for BincodeDencoder {
     fn encode<T: Serialize>(value: T) -> Result<Vec<u8>, super::Error> {
-        bincode::serialize(&value).map_err(|e| super::Error::Encode(e.to_string()))
+        bincode::serialize(&value)
+            .map(|bytes| with_format(FIXINT_FORMAT, bytes))
+            .map_err(|e| super::Error::Encode(e.to_string()))
     }
 
     fn decode<U: DeserializeOwned>(value: Vec<u8>) -> Result<U, super::Error> {
-        bincode::deserialize(&value).map_err(|e| super::Error::Decode(e.to_string()))
+        bincode::deserialize(strip_format(FIXINT_FORMAT, &value)?)
+            .map_err(|e| super::Error::Decode(e.to_string()))
+    }
+}
+
+///
+/// Dencoder implemented over Bincode, encoding integers with a variable size
+///
+/// more compact for small integers, but not wire-compatible with [`BincodeDencoder`];
+/// both ends must use the same one. output is prefixed with [`VARINT_FORMAT`].
+///
+#[derive(Debug)]
+#[deprecated(note = "bincode is unmaintained, use the bitcode Dencoder if possible")]
+pub struct BincodeVarintDencoder;
+
+fn varint_options() -> impl Options {
+    bincode::DefaultOptions::new().with_varint_encoding()
+}
+
+#[allow(deprecated)]
+impl Dencoder for BincodeVarintDencoder {
+    fn encode<T: Serialize>(value: T) -> Result<Vec<u8>, super::Error> {
+        varint_options()
+            .serialize(&value)
+            .map(|bytes| with_format(VARINT_FORMAT, bytes))
+            .map_err(|e| super::Error::Encode(e.to_string()))
+    }
+
+    fn decode<U: DeserializeOwned>(value: Vec<u8>) -> Result<U, super::Error> {
+        varint_options()
+            .deserialize(strip_format(VARINT_FORMAT, &value)?)
+            .map_err(|e| super::Error::Decode(e.to_string()))
     }
 }
 
@@ -33,8 +76,11 @@ mod tests {
 
     use serde::{Deserialize, Serialize};
 
-    use super::BincodeDencoder;
-    use crate::actors::remote::dencoder::Dencoder;
+    use super::{BincodeDencoder, BincodeVarintDencoder};
+    use crate::{
+        actors::remote::dencoder::{Dencoder, Error},
+        messaging::Message,
+    };
 
     const TEST_STRING: &str = "a ü string ⅞123";
 
@@ -65,4 +111,63 @@ mod tests {
 
         assert_eq!(foo, foo_dec);
     }
+
+    #[test]
+    fn varint_decode_and_encode() {
+        let foo = Foo::new();
+
+        let foo_enc = BincodeVarintDencoder::encode(foo.clone()).unwrap();
+
+        let foo_dec = BincodeVarintDencoder::decode(foo_enc).unwrap();
+
+        assert_eq!(foo, foo_dec);
+    }
+
+    #[test]
+    fn varint_is_smaller() {
+        let small = vec![1u64, 2, 3, 4];
+
+        let fixint = BincodeDencoder::encode(small.clone()).unwrap();
+        let varint = BincodeVarintDencoder::encode(small).unwrap();
+
+        assert_eq!(fixint.len(), 1 + 8 + 4 * 8);
+        assert_eq!(varint.len(), 1 + 1 + 4);
+    }
+
+    #[test]
+    fn varint_trailing_bytes() {
+        let mut foo_enc = BincodeVarintDencoder::encode(Foo::new()).unwrap();
+        foo_enc.extend_from_slice(&[0xde, 0xad]);
+
+        let res = BincodeVarintDencoder::decode::<Foo>(foo_enc);
+        assert!(matches!(res, Err(Error::Decode(_))));
+    }
+
+    #[test]
+    fn message_variants_stable() {
+        // variants from 0.3.0 keep their index, newer ones are appended
+        let variant = |msg: Message<u8>| BincodeDencoder::encode(msg).unwrap()[1];
+
+        assert_eq!(variant(Message::Task(0)), 0);
+        assert_eq!(variant(Message::TaskMut(0)), 1);
+        assert_eq!(variant(Message::Ping), 2);
+        assert_eq!(variant(Message::Stop), 3);
+    }
+
+    #[test]
+    fn format_mismatch() {
+        let foo = Foo::new();
+
+        let varint = BincodeVarintDencoder::encode(foo.clone()).unwrap();
+        let res = BincodeDencoder::decode::<Foo>(varint);
+        assert!(matches!(res, Err(Error::Decode(ctx)) if ctx.contains("format version mismatch")));
+
+        let mut fixint = BincodeDencoder::encode(foo).unwrap();
+        fixint[0] = 42;
+        let res = BincodeDencoder::decode::<Foo>(fixint);
+        assert!(matches!(res, Err(Error::Decode(ctx)) if ctx.contains("got 42")));
+
+        let res = BincodeDencoder::decode::<Foo>(vec![]);
+        assert!(matches!(res, Err(Error::Decode(_))));
+    }
 }
@@ -2,21 +2,29 @@
 //! Bitcode-based Dencoder implementation
 //!
 
-use crate::actors::remote::dencoder::Dencoder;
+use crate::actors::remote::dencoder::{Dencoder, strip_format, with_format};
+
+/// format version prefixed to [`BitcodeDencoder`] output
+pub const FORMAT: u8 = 3;
 
 ///
 /// Bitcode-based Dencoder implementation
 ///
+/// output is prefixed with [`FORMAT`], so decoding another dencoder's output fails.
+///
 #[derive(Debug)]
 pub struct BitcodeDencoder;
 
 impl Dencoder for BitcodeDencoder {
     fn encode<T: serde::Serialize>(value: T) -> Result<Vec<u8>, super::Error> {
-        bincode::serialize(&value).map_err(|e| super::Error::Encode(e.to_string()))
+        bincode::serialize(&value)
+            .map(|bytes| with_format(FORMAT, bytes))
+            .map_err(|e| super::Error::Encode(e.to_string()))
     }
 
     fn decode<U: serde::de::DeserializeOwned>(value: Vec<u8>) -> Result<U, super::Error> {
-        bincode::deserialize(&value).map_err(|e| super::Error::Decode(e.to_string()))
+        bincode::deserialize(strip_format(FORMAT, &value)?)
+            .map_err(|e| super::Error::Decode(e.to_string()))
     }
 }
 
@@ -2,16 +2,32 @@
 //! abstractions for network layers and implementations
 //!
 
-use std::future::Future;
+use std::{fmt::Display, future::Future};
 
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
+pub mod tee;
+
 #[cfg(feature = "tcp")]
 pub mod tcp_layer;
 
 #[cfg(feature = "tor")]
 pub mod tor_layer;
 
+///
+/// protocol IDs (see [`NetLayer::name()`]) of the net layers compiled into this build
+///
+pub fn available_protocols() -> &'static [&'static str] {
+    const PROTOCOLS: &[&str] = &[
+        #[cfg(feature = "tcp")]
+        "tcp",
+        #[cfg(feature = "tor")]
+        "tor",
+    ];
+
+    PROTOCOLS
+}
+
 ///
 /// trait for AsyncRead + AsyncWrite streams used in routers
 ///
@@ -19,12 +35,27 @@ pub trait AsyncMsgStream: AsyncReadExt + AsyncWriteExt + Unpin + Send + 'static
 
 impl<T> AsyncMsgStream for T where T: AsyncReadExt + AsyncWriteExt + Unpin + Send + 'static {}
 
+///
+/// errors returned by a [`NetLayer`]
+///
+pub trait NetLayerError: Display {
+    ///
+    /// whether this error leaves the net layer unusable.
+    ///
+    /// routers stop when accepting a connection fails with a fatal error, and keep going otherwise.
+    /// not fatal by default.
+    ///
+    fn is_fatal(&self) -> bool {
+        false
+    }
+}
+
 ///
 /// net layer trait abstracting over async streams
 ///
 pub trait NetLayer {
     /// errors during net layer operation
-    type Error;
+    type Error: NetLayerError;
 
     ///
     /// net layer's identifier, using during actor address construction
@@ -44,6 +75,16 @@ pub trait NetLayer {
     ///
     fn init(&mut self) -> impl Future<Output = Result<(), Self::Error>> + Send;
 
+    ///
+    /// wait until this net layer is actually able to accept connections.
+    ///
+    /// some layers (e.g. Tor) may take a while after [`Self::init()`] before they are
+    /// reachable. ready immediately by default.
+    ///
+    fn ready(&self) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        async { Ok(()) }
+    }
+
     ///
     /// wait for and accept the next connection
     ///
@@ -53,4 +94,74 @@ pub trait NetLayer {
     /// this net layer's exposed address
     ///
     fn address(&self) -> impl Future<Output = Result<String, Self::Error>>;
+
+    ///
+    /// stable address remote peers should dial, if it differs from what [`Self::address()`] reports
+    /// (e.g. behind NAT or a proxy). routers prefer it when building actor addresses.
+    ///
+    /// none by default.
+    ///
+    fn public_address(&self) -> Result<Option<String>, Self::Error> {
+        Ok(None)
+    }
+
+    ///
+    /// whether this net layer can currently carry traffic, e.g. for showing it in a UI before sending.
+    ///
+    /// unlike [`Self::ready()`], this doesn't wait. true by default.
+    ///
+    fn is_ready(&self) -> bool {
+        true
+    }
+
+    ///
+    /// check whether `host` is well-formed for this net layer, without dialing it.
+    ///
+    /// accepts anything by default.
+    ///
+    fn validate_host(_host: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "tcp")]
+    #[test]
+    fn tcp_is_available() {
+        use super::{NetLayer, tcp_layer::TcpNetLayer};
+
+        assert!(super::available_protocols().contains(&TcpNetLayer::name()));
+    }
+
+    #[cfg(all(feature = "tcp", feature = "tor"))]
+    #[test]
+    fn protocols_match_names() {
+        use super::{NetLayer, tcp_layer::TcpNetLayer, tor_layer::TorLayer};
+
+        assert_eq!(
+            super::available_protocols(),
+            [TcpNetLayer::name(), TorLayer::name()]
+        );
+    }
+
+    #[cfg(all(feature = "tcp", feature = "tor"))]
+    #[test]
+    fn validate_host() {
+        use super::{NetLayer, tcp_layer::TcpNetLayer, tor_layer::TorLayer};
+
+        let onion = "mfrggzdfmztwq2lknnwg23tpobyxe43uov3ho6dzpjqwgzlnmfrggzdf.onion:1234";
+        assert!(TorLayer::validate_host(onion).is_ok());
+        assert!(TcpNetLayer::validate_host(onion).is_err());
+
+        let tcp = "127.0.0.1:1234";
+        assert!(TcpNetLayer::validate_host(tcp).is_ok());
+        assert!(TorLayer::validate_host(tcp).is_err());
+
+        assert!(TcpNetLayer::validate_host("localhost:1234").is_ok());
+        assert!(TcpNetLayer::validate_host("[::1]:1234").is_ok());
+        assert!(TcpNetLayer::validate_host("127.0.0.1").is_err());
+        assert!(TorLayer::validate_host("tooshort.onion:1234").is_err());
+        assert!(TorLayer::validate_host(onion.trim_end_matches(":1234")).is_err());
+    }
 }
@@ -4,11 +4,14 @@
 //! !WARNING! for testing only! nothing going through these is encrypted!
 //!
 
-use std::fmt::Display;
+use std::{
+    fmt::Display,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
 
 use tokio::net::{TcpListener, TcpStream};
 
-use super::{AsyncMsgStream, NetLayer};
+use super::{AsyncMsgStream, NetLayer, NetLayerError};
 
 ///
 /// simple TCP net layer
@@ -18,6 +21,7 @@ use super::{AsyncMsgStream, NetLayer};
 #[derive(Debug)]
 pub struct TcpNetLayer {
     listener: Option<TcpListener>,
+    public_address: Option<String>,
 }
 
 impl TcpNetLayer {
@@ -27,8 +31,28 @@ impl TcpNetLayer {
     pub fn new() -> Self {
         Self {
             listener: Option::None,
+            public_address: None,
         }
     }
+
+    ///
+    /// create a TCP net layer serving on an already bound listener, e.g. one handed over
+    /// by socket activation. [`NetLayer::init()`] won't bind a new one.
+    ///
+    pub fn from_listener(listener: TcpListener) -> Self {
+        Self {
+            listener: Some(listener),
+            public_address: None,
+        }
+    }
+
+    ///
+    /// address peers should dial instead of the one we bind to. see [`NetLayer::public_address()`].
+    ///
+    pub fn with_public_address(mut self, public_address: impl Into<String>) -> Self {
+        self.public_address = Some(public_address.into());
+        self
+    }
 }
 
 impl Default for TcpNetLayer {
@@ -53,6 +77,10 @@ impl NetLayer for TcpNetLayer {
     }
 
     async fn init(&mut self) -> Result<(), Self::Error> {
+        if self.listener.is_some() {
+            return Ok(());
+        }
+
         self.listener
             .replace(TcpListener::bind("0.0.0.0:0").await.map_err(|e| {
                 tracing::error!("bind error: {e}");
@@ -79,13 +107,37 @@ impl NetLayer for TcpNetLayer {
     }
 
     async fn address(&self) -> Result<String, Self::Error> {
-        Ok(self
+        let mut addr = self
             .listener
             .as_ref()
             .ok_or(TcpError::NotReady)?
             .local_addr()
-            .map_err(|_| TcpError::NotReady)?
-            .to_string())
+            .map_err(|_| TcpError::NotReady)?;
+
+        // can't dial an unspecified address, so hand out loopback instead
+        if addr.ip().is_unspecified() {
+            addr.set_ip(match addr.ip() {
+                IpAddr::V4(_) => Ipv4Addr::LOCALHOST.into(),
+                IpAddr::V6(_) => Ipv6Addr::LOCALHOST.into(),
+            });
+        }
+
+        Ok(addr.to_string())
+    }
+
+    fn public_address(&self) -> Result<Option<String>, Self::Error> {
+        Ok(self.public_address.clone())
+    }
+
+    fn validate_host(host: &str) -> Result<(), Self::Error> {
+        let invalid = || TcpError::InvalidHost(host.to_string());
+        let (name, port) = host.rsplit_once(':').ok_or_else(invalid)?;
+
+        if name.is_empty() || name.ends_with(".onion") || port.parse::<u16>().is_err() {
+            return Err(invalid());
+        }
+
+        Ok(())
     }
 }
 
@@ -99,6 +151,7 @@ pub enum TcpError {
     Bind(String),
     Accept(String),
     Connect(String),
+    InvalidHost(String),
 }
 
 impl Display for TcpError {
@@ -108,17 +161,37 @@ impl Display for TcpError {
             TcpError::Bind(ctx) => write!(f, "failed to bind to address: {ctx}"),
             TcpError::Accept(ctx) => write!(f, "failed to accept connection: {ctx}"),
             TcpError::Connect(ctx) => write!(f, "failed to connect to address: {ctx}"),
+            TcpError::InvalidHost(host) => write!(f, "not a host:port address: {host}"),
         }
     }
 }
 
 impl std::error::Error for TcpError {}
 
+impl NetLayerError for TcpError {
+    fn is_fatal(&self) -> bool {
+        matches!(self, TcpError::NotReady | TcpError::Bind(_))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::time::Duration;
 
-    use crate::actors::remote::netlayer::{NetLayer, tcp_layer::TcpNetLayer};
+    use tokio::net::TcpListener;
+
+    use crate::{
+        actors::{
+            remote::{
+                self,
+                dencoder::bitcode::BitcodeDencoder,
+                netlayer::{NetLayer, tcp_layer::TcpNetLayer},
+                router::{RemoteHandle, Router, RouterOpts},
+            },
+            tests::{Mult, SomeError},
+        },
+        messaging::{Message, Reply},
+    };
 
     #[tokio::test]
     async fn listen() {
@@ -126,6 +199,38 @@ mod tests {
         nl.init().await.unwrap();
     }
 
+    #[tokio::test]
+    async fn ready() {
+        let mut nl = TcpNetLayer::new();
+        nl.init().await.unwrap();
+
+        tokio::time::timeout(Duration::from_millis(100), nl.ready())
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn dialable_address() {
+        let mut nl = TcpNetLayer::new();
+        nl.init().await.unwrap();
+
+        let addr = nl.address().await.unwrap();
+        assert!(!addr.starts_with("0.0.0.0"));
+
+        tokio::spawn(async move {
+            let _ = nl.accept().await;
+        });
+
+        tokio::time::timeout(
+            Duration::from_millis(1000),
+            TcpNetLayer::new().connect(&addr),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    }
+
     #[tokio::test]
     async fn accept() {
         let mut nl = TcpNetLayer::new();
@@ -164,4 +269,32 @@ mod tests {
             .unwrap()
             .unwrap();
     }
+
+    #[tokio::test]
+    async fn from_listener() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let bound = listener.local_addr().unwrap().to_string();
+
+        let router = Router::with_netlayer(
+            TcpNetLayer::from_listener(listener),
+            Some(RouterOpts::default()),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(router.host_address(), bound);
+
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+        let addr = router.attach(handle).await.unwrap();
+
+        let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        let res = remote.send(Message::Task(5)).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Task(15))));
+    }
 }
@@ -0,0 +1,271 @@
+//!
+//! net layer wrapper recording the traffic going through it
+//!
+
+use std::{
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    task::{Context, Poll},
+};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use super::{AsyncMsgStream, NetLayer};
+
+///
+/// transparent wrapper over another net layer, counting the bytes going through its streams.
+///
+/// raw bytes are also captured with the `debug_wire` feature.
+///
+#[derive(Debug)]
+pub struct Tee<N> {
+    inner: N,
+    stats: Arc<TeeStats>,
+}
+
+impl<N> Tee<N> {
+    /// wrap this net layer
+    pub fn new(inner: N) -> Self {
+        Self {
+            inner,
+            stats: Arc::new(TeeStats::default()),
+        }
+    }
+
+    /// traffic recorded so far, shared with every stream this layer hands out
+    pub fn stats(&self) -> Arc<TeeStats> {
+        self.stats.clone()
+    }
+
+    /// wrapped net layer
+    pub fn inner(&self) -> &N {
+        &self.inner
+    }
+}
+
+impl<N> NetLayer for Tee<N>
+where
+    N: NetLayer + Send + Sync,
+{
+    type Error = N::Error;
+
+    fn name() -> &'static str {
+        N::name()
+    }
+
+    async fn connect(&self, addr: &str) -> Result<impl AsyncMsgStream, Self::Error> {
+        let stream = self.inner.connect(addr).await?;
+
+        Ok(TeeStream::new(stream, self.stats.clone()))
+    }
+
+    async fn init(&mut self) -> Result<(), Self::Error> {
+        self.inner.init().await
+    }
+
+    async fn ready(&self) -> Result<(), Self::Error> {
+        self.inner.ready().await
+    }
+
+    async fn accept(&self) -> Result<impl AsyncMsgStream, Self::Error> {
+        let stream = self.inner.accept().await?;
+
+        Ok(TeeStream::new(stream, self.stats.clone()))
+    }
+
+    async fn address(&self) -> Result<String, Self::Error> {
+        self.inner.address().await
+    }
+
+    fn public_address(&self) -> Result<Option<String>, Self::Error> {
+        self.inner.public_address()
+    }
+
+    fn is_ready(&self) -> bool {
+        self.inner.is_ready()
+    }
+
+    fn validate_host(host: &str) -> Result<(), Self::Error> {
+        N::validate_host(host)
+    }
+}
+
+///
+/// traffic recorded by a [`Tee`]. "in" is what was read from its streams, "out" what was written.
+///
+#[derive(Debug, Default)]
+pub struct TeeStats {
+    connections: AtomicU64,
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    #[cfg(feature = "debug_wire")]
+    raw_in: std::sync::Mutex<Vec<u8>>,
+    #[cfg(feature = "debug_wire")]
+    raw_out: std::sync::Mutex<Vec<u8>>,
+}
+
+impl TeeStats {
+    /// streams opened, either connecting or accepting
+    pub fn connections(&self) -> u64 {
+        self.connections.load(Ordering::Relaxed)
+    }
+
+    /// total bytes read
+    pub fn bytes_in(&self) -> u64 {
+        self.bytes_in.load(Ordering::Relaxed)
+    }
+
+    /// total bytes written
+    pub fn bytes_out(&self) -> u64 {
+        self.bytes_out.load(Ordering::Relaxed)
+    }
+
+    /// every byte read so far, across streams
+    #[cfg(feature = "debug_wire")]
+    pub fn raw_in(&self) -> Vec<u8> {
+        self.raw_in.lock().expect("poisoned lock").clone()
+    }
+
+    /// every byte written so far, across streams
+    #[cfg(feature = "debug_wire")]
+    pub fn raw_out(&self) -> Vec<u8> {
+        self.raw_out.lock().expect("poisoned lock").clone()
+    }
+
+    fn record_in(&self, bytes: &[u8]) {
+        self.bytes_in
+            .fetch_add(bytes.len() as u64, Ordering::Relaxed);
+
+        #[cfg(feature = "debug_wire")]
+        self.raw_in
+            .lock()
+            .expect("poisoned lock")
+            .extend_from_slice(bytes);
+    }
+
+    fn record_out(&self, bytes: &[u8]) {
+        self.bytes_out
+            .fetch_add(bytes.len() as u64, Ordering::Relaxed);
+
+        #[cfg(feature = "debug_wire")]
+        self.raw_out
+            .lock()
+            .expect("poisoned lock")
+            .extend_from_slice(bytes);
+    }
+}
+
+///
+/// stream handed out by a [`Tee`], recording what goes through it
+///
+#[derive(Debug)]
+pub struct TeeStream<S> {
+    inner: S,
+    stats: Arc<TeeStats>,
+}
+
+impl<S> TeeStream<S> {
+    fn new(inner: S, stats: Arc<TeeStats>) -> Self {
+        stats.connections.fetch_add(1, Ordering::Relaxed);
+
+        Self { inner, stats }
+    }
+}
+
+impl<S> AsyncRead for TeeStream<S>
+where
+    S: AsyncRead + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let start = buf.filled().len();
+        let res = Pin::new(&mut self.inner).poll_read(cx, buf);
+
+        if let Poll::Ready(Ok(())) = res {
+            self.stats.record_in(&buf.filled()[start..]);
+        }
+
+        res
+    }
+}
+
+impl<S> AsyncWrite for TeeStream<S>
+where
+    S: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let res = Pin::new(&mut self.inner).poll_write(cx, buf);
+
+        if let Poll::Ready(Ok(written)) = res {
+            self.stats.record_out(&buf[..written]);
+        }
+
+        res
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use crate::{
+        actors::{
+            remote::{
+                self,
+                dencoder::bitcode::BitcodeDencoder,
+                netlayer::{tcp_layer::TcpNetLayer, tee::Tee},
+                router::{RemoteHandle, Router, RouterOpts},
+            },
+            tests::{Mult, SomeError},
+        },
+        messaging::{Message, Reply},
+    };
+
+    #[tokio::test]
+    async fn records_traffic() {
+        let server = Tee::new(TcpNetLayer::new());
+        let server_stats = server.stats();
+
+        let router = Router::with_netlayer(server, Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+        let addr = router.attach(handle).await.unwrap();
+
+        let client = Tee::new(TcpNetLayer::new());
+        let client_stats = client.stats();
+
+        let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, _>::new(&addr, client);
+
+        let res = remote.send(Message::Task(5)).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Task(15))));
+
+        assert_eq!(client_stats.connections(), 1);
+        assert_eq!(server_stats.connections(), 1);
+
+        assert!(client_stats.bytes_out() > 0);
+        assert!(client_stats.bytes_in() > 0);
+
+        assert_eq!(client_stats.bytes_out(), server_stats.bytes_in());
+        assert_eq!(client_stats.bytes_in(), server_stats.bytes_out());
+    }
+}
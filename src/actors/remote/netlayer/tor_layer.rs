@@ -3,22 +3,38 @@
 //!
 
 use std::sync::Arc;
-use std::{fmt::Display, time::Duration};
+use std::{
+    fmt::Display,
+    future::Future,
+    time::{Duration, Instant},
+};
 
 use arti_client::config::TorClientConfigBuilder;
 use arti_client::{TorClient, TorClientConfig};
+use futures::channel::oneshot;
 use futures::lock::Mutex;
 use futures::{Stream, StreamExt};
 use safelog::DisplayRedacted;
 use tor_cell::relaycell::msg::Connected;
 use tor_hsservice::config::OnionServiceConfigBuilder;
-use tor_hsservice::{RunningOnionService, StreamRequest};
+use tor_hsservice::{HsNickname, RunningOnionService, StreamRequest};
 use tor_proto::client::stream::IncomingStreamRequest;
 use tor_rtcompat::PreferredRuntime;
 
-use crate::actors::remote::netlayer::{AsyncMsgStream, NetLayer};
+use crate::actors::remote::netlayer::{AsyncMsgStream, NetLayer, NetLayerError};
 use crate::utils;
 
+pub use arti_client::status::BootstrapStatus;
+pub use tor_hsservice::status::OnionServiceStatus;
+
+const DEFAULT_REACHABILITY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// wrong-port streams rejected in a row before `accept` yields back to the runtime
+const MAX_REJECTIONS_PER_YIELD: usize = 32;
+
+/// minimum time between two warnings about wrong-port streams
+const WRONG_PORT_WARNING_INTERVAL: Duration = Duration::from_secs(30);
+
 ///
 /// Tor netlayer powered by Arti
 ///
@@ -26,12 +42,16 @@ use crate::utils;
 pub struct TorLayer {
     client: TorClient<PreferredRuntime>,
     nickname: String,
-    port: Option<u16>,
+    virtual_port: Option<u16>,
+    advertised_port: Option<u16>,
     address: Option<String>,
     service: Option<Arc<RunningOnionService>>,
 
     // here lies a testament to my inadequacy
     stream: Option<Arc<Mutex<Box<dyn Stream<Item = StreamRequest> + Send + Unpin>>>>,
+
+    reachability_timeout: Duration,
+    last_wrong_port_warning: std::sync::Mutex<Option<Instant>>,
 }
 
 ///
@@ -39,24 +59,38 @@ pub struct TorLayer {
 ///
 #[derive(Debug)]
 pub struct TorLayerConfig {
-    port: u16,
+    virtual_port: u16,
+    advertised_port: u16,
     directories: Option<TorLayerDirectories>,
 }
 
 impl TorLayerConfig {
     /// create the desired configuration for this Tor layer
     pub fn new(port: u16, directories: TorLayerDirectories) -> Self {
-        Self {
-            port,
-            directories: Some(directories),
-        }
+        Self::new_with_ports(port, port, Some(directories))
     }
 
     /// create the configuration desired with only a port number
     pub fn new_from_port(port: u16) -> Self {
+        Self::new_with_ports(port, port, None)
+    }
+
+    ///
+    /// create the configuration desired with the onion service listening on `virtual_port`,
+    /// but advertising `advertised_port` in its address.
+    ///
+    /// streams for either port are accepted, so the advertised address can be dialed as is
+    /// while clients that know the virtual port keep working.
+    ///
+    pub fn new_with_ports(
+        virtual_port: u16,
+        advertised_port: u16,
+        directories: Option<TorLayerDirectories>,
+    ) -> Self {
         Self {
-            port,
-            directories: None,
+            virtual_port,
+            advertised_port,
+            directories,
         }
     }
 }
@@ -85,17 +119,8 @@ impl TorLayer {
     /// boostrap a Tor circuit ready for either making remote connections or creating a new Router
     ///
     pub async fn new(nickname: String, layer_config: TorLayerConfig) -> Result<Self, Error> {
-        let conf = if let Some(TorLayerDirectories {
-            data_dir,
-            cache_dir,
-        }) = layer_config.directories
-        {
-            TorClientConfigBuilder::from_directories(data_dir, cache_dir)
-                .build()
-                .map_err(|e| Error::Bootstrap(e.to_string()))?
-        } else {
-            TorClientConfig::default()
-        };
+        check_nickname(&nickname)?;
+        let conf = client_config(layer_config.directories)?;
 
         let client = TorClient::create_bootstrapped(conf)
             .await
@@ -104,19 +129,81 @@ impl TorLayer {
         Ok(Self {
             client,
             nickname,
-            port: Some(layer_config.port),
+            virtual_port: Some(layer_config.virtual_port),
+            advertised_port: Some(layer_config.advertised_port),
             address: None,
             service: None,
             stream: None,
+            reachability_timeout: DEFAULT_REACHABILITY_TIMEOUT,
+            last_wrong_port_warning: Default::default(),
         })
     }
 
+    ///
+    /// like [`Self::new`], but also returning a stream of bootstrap progress events.
+    ///
+    /// nothing happens until the returned future is polled. the progress stream ends once
+    /// bootstrapping either completes or fails.
+    ///
+    pub fn new_with_progress(
+        nickname: String,
+        layer_config: TorLayerConfig,
+    ) -> (
+        impl Future<Output = Result<Self, Error>> + Send,
+        impl Stream<Item = BootstrapStatus> + Send,
+    ) {
+        let client = check_nickname(&nickname).and_then(|_| {
+            let conf = client_config(layer_config.directories)?;
+
+            TorClient::builder()
+                .config(conf)
+                .create_unbootstrapped()
+                .map_err(|e| Error::Bootstrap(e.to_string()))
+        });
+
+        let events = client.as_ref().ok().map(|client| client.bootstrap_events());
+        let (done_sender, done_receiver) = oneshot::channel::<()>();
+
+        let virtual_port = layer_config.virtual_port;
+        let advertised_port = layer_config.advertised_port;
+        let layer = async move {
+            // dropped on completion or failure, ending the progress stream
+            let _done = done_sender;
+
+            let client = client?;
+            client
+                .bootstrap()
+                .await
+                .map_err(|e| Error::Bootstrap(e.to_string()))?;
+
+            Ok(Self {
+                client,
+                nickname,
+                virtual_port: Some(virtual_port),
+                advertised_port: Some(advertised_port),
+                address: None,
+                service: None,
+                stream: None,
+                reachability_timeout: DEFAULT_REACHABILITY_TIMEOUT,
+                last_wrong_port_warning: Default::default(),
+            })
+        };
+
+        let progress = futures::stream::iter(events)
+            .flatten()
+            .take_until(done_receiver);
+
+        (layer, progress)
+    }
+
     ///
     /// bootstrap a Tor circuit for making connections. note that a layer created this
     /// way will get a port assigned at random. if you want to chose the port, use
     /// [Self::new] instead.
     ///
     pub async fn new_for_client(nickname: String) -> Result<Self, Error> {
+        check_nickname(&nickname)?;
+
         let client = TorClient::create_bootstrapped(TorClientConfig::default())
             .await
             .map_err(|e| Error::Bootstrap(e.to_string()))?;
@@ -124,12 +211,57 @@ impl TorLayer {
         Ok(Self {
             client,
             nickname,
-            port: None,
+            virtual_port: None,
+            advertised_port: None,
             address: None,
             service: None,
             stream: None,
+            reachability_timeout: DEFAULT_REACHABILITY_TIMEOUT,
+            last_wrong_port_warning: Default::default(),
         })
     }
+
+    ///
+    /// how long [`NetLayer::ready()`] waits for the onion service to become reachable.
+    ///
+    /// defaults to 60 seconds. zero skips the wait, returning as soon as the service is published.
+    ///
+    pub fn with_reachability_timeout(mut self, timeout: Duration) -> Self {
+        self.reachability_timeout = timeout;
+        self
+    }
+
+    ///
+    /// latest status reported by our onion service, e.g. to tell a service that's still
+    /// publishing from a reachable one.
+    ///
+    /// none until the service is launched by [`NetLayer::init()`].
+    ///
+    pub fn last_status(&self) -> Option<OnionServiceStatus> {
+        self.service.as_ref().map(|service| service.status())
+    }
+}
+
+/// fail early on nicknames the onion service would refuse once bootstrapped
+fn check_nickname(nickname: &str) -> Result<(), Error> {
+    nickname
+        .parse::<HsNickname>()
+        .map(|_| ())
+        .map_err(|e| Error::Init(format!("invalid nickname: {e}")))
+}
+
+fn client_config(directories: Option<TorLayerDirectories>) -> Result<TorClientConfig, Error> {
+    if let Some(TorLayerDirectories {
+        data_dir,
+        cache_dir,
+    }) = directories
+    {
+        TorClientConfigBuilder::from_directories(data_dir, cache_dir)
+            .build()
+            .map_err(|e| Error::Bootstrap(e.to_string()))
+    } else {
+        Ok(TorClientConfig::default())
+    }
 }
 
 impl NetLayer for TorLayer {
@@ -162,37 +294,23 @@ impl NetLayer for TorLayer {
             .map_err(|e| Error::Init(e.to_string()))?
             .ok_or(Error::Init("could not launch onion service".to_string()))?;
 
-        let status_stream = service.status_events();
-        let mut binding = status_stream
-            .filter(|status| futures::future::ready(status.state().is_fully_reachable()));
+        if self.virtual_port.is_none() {
+            let port = utils::random_unused_port()
+                .await
+                .map_err(|e| Error::Hostname(e.to_string()))?;
 
-        match tokio::time::timeout(Duration::from_secs(60), binding.next()).await {
-            Ok(Some(_)) => tracing::info!("onion service is fully reachable."),
-            Ok(None) => tracing::warn!("status stream ended unexpectedly."),
-            Err(_) => tracing::warn!(
-                "timeout waiting for service to become reachable. actor may or may not receive messages."
-            ),
-        };
-
-        if self.port.is_none() {
-            let port = self.port.unwrap_or(
-                utils::random_unused_port()
-                    .await
-                    .map_err(|e| Error::Hostname(e.to_string()))?,
-            );
-
-            self.port.replace(port);
+            self.virtual_port.replace(port);
         }
 
+        let advertised_port = *self
+            .advertised_port
+            .get_or_insert(self.virtual_port.expect("valid port should be set"));
+
         let redacted = service.onion_address().ok_or(Error::Init(
             "failed to query our own onion address".to_string(),
         ))?;
 
-        let address = format!(
-            "{}:{}",
-            redacted.display_unredacted(),
-            self.port.expect("valid port should be set")
-        );
+        let address = format!("{}:{}", redacted.display_unredacted(), advertised_port);
 
         let requests_stream = tor_hsservice::handle_rend_requests(requests_stream);
 
@@ -204,31 +322,84 @@ impl NetLayer for TorLayer {
         Ok(())
     }
 
+    async fn ready(&self) -> Result<(), Self::Error> {
+        let service = self.service.as_ref().ok_or(Error::NotReady)?;
+
+        if self.reachability_timeout.is_zero() {
+            tracing::info!("skipping wait for onion service reachability.");
+            return Ok(());
+        }
+
+        let status_stream = service.status_events();
+        let mut binding = status_stream
+            .filter(|status| futures::future::ready(status.state().is_fully_reachable()));
+
+        match tokio::time::timeout(self.reachability_timeout, binding.next()).await {
+            Ok(Some(_)) => tracing::info!("onion service is fully reachable."),
+            Ok(None) => tracing::warn!("status stream ended unexpectedly."),
+            Err(_) => tracing::warn!(
+                "timeout waiting for service to become reachable (state: {:?}). actor may or may not receive messages.",
+                service.status().state()
+            ),
+        };
+
+        Ok(())
+    }
+
     async fn accept(&self) -> Result<impl AsyncMsgStream, Self::Error> {
-        loop {
-            if let Some(stream) = &self.stream {
-                if let Some(request) = stream.lock().await.next().await {
-                    match request.request() {
-                        IncomingStreamRequest::Begin(begin)
-                            if begin.port() == self.port.expect("valid port should be set") =>
-                        {
-                            return request
-                                .accept(Connected::new_empty())
-                                .await
-                                .map_err(|e| Error::Accept(e.to_string()));
-                        }
-                        _ => {
-                            let _ = request.shutdown_circuit();
-                            continue;
-                        }
-                    }
-                } else {
-                    return Err(Error::NotReady);
-                }
-            } else {
-                return Err(Error::NotReady);
-            }
+        let stream = self.stream.as_ref().ok_or(Error::NotReady)?;
+        let port = self.virtual_port.expect("valid port should be set");
+        let advertised_port = self.advertised_port.unwrap_or(port);
+
+        let mut requests = stream.lock().await;
+        let request = next_matching(
+            &mut *requests,
+            |request| {
+                matches!(
+                    request.request(),
+                    IncomingStreamRequest::Begin(begin)
+                        if begin.port() == port || begin.port() == advertised_port
+                )
+            },
+            |request| {
+                let _ = request.shutdown_circuit();
+            },
+            &self.last_wrong_port_warning,
+        )
+        .await
+        .ok_or(Error::NotReady)?;
+
+        request
+            .accept(Connected::new_empty())
+            .await
+            .map_err(|e| Error::Accept(e.to_string()))
+    }
+
+    fn public_address(&self) -> Result<Option<String>, Self::Error> {
+        // onion addresses are public already
+        Ok(self.address.clone())
+    }
+
+    fn is_ready(&self) -> bool {
+        self.client.bootstrap_status().ready_for_traffic()
+    }
+
+    fn validate_host(host: &str) -> Result<(), Self::Error> {
+        let invalid = || Error::InvalidHost(host.to_string());
+        let (name, port) = host.rsplit_once(':').ok_or_else(invalid)?;
+        let label = name.strip_suffix(".onion").ok_or_else(invalid)?;
+
+        // v3 onion addresses are 56 base32 characters
+        let is_v3 = label.len() == 56
+            && label
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || (b'2'..=b'7').contains(&b));
+
+        if !is_v3 || port.parse::<u16>().is_err() {
+            return Err(invalid());
         }
+
+        Ok(())
     }
 
     async fn address(&self) -> Result<String, Self::Error> {
@@ -236,6 +407,52 @@ impl NetLayer for TorLayer {
     }
 }
 
+///
+/// next request passing `wanted`, rejecting the others.
+///
+/// yields back to the runtime every [`MAX_REJECTIONS_PER_YIELD`] rejections in a row, so a flood
+/// of unwanted requests can't keep the caller's task busy forever, and warns about it at most
+/// once per [`WRONG_PORT_WARNING_INTERVAL`].
+///
+async fn next_matching<S, T>(
+    requests: &mut S,
+    wanted: impl Fn(&T) -> bool,
+    reject: impl Fn(T),
+    last_warning: &std::sync::Mutex<Option<Instant>>,
+) -> Option<T>
+where
+    S: Stream<Item = T> + Unpin + ?Sized,
+{
+    let mut rejected = 0;
+
+    loop {
+        let request = requests.next().await?;
+
+        if wanted(&request) {
+            return Some(request);
+        }
+
+        reject(request);
+        rejected += 1;
+
+        if rejected == MAX_REJECTIONS_PER_YIELD {
+            rejected = 0;
+
+            warn_wrong_ports(last_warning);
+            tokio::task::yield_now().await;
+        }
+    }
+}
+
+fn warn_wrong_ports(last_warning: &std::sync::Mutex<Option<Instant>>) {
+    let mut last_warning = last_warning.lock().expect("poisoned lock");
+
+    if last_warning.is_none_or(|last| last.elapsed() >= WRONG_PORT_WARNING_INTERVAL) {
+        tracing::warn!("tor layer: rejecting many streams for the wrong port");
+        *last_warning = Some(Instant::now());
+    }
+}
+
 ///
 /// errors when binding, accepting and connecting via a Tor net layer
 ///
@@ -247,6 +464,7 @@ pub enum Error {
     Accept(String),
     Connect(String),
     Hostname(String),
+    InvalidHost(String),
     NotReady,
 }
 
@@ -257,6 +475,7 @@ impl Display for Error {
             Error::Accept(ctx) => write!(f, "failed to receive data: {ctx}"),
             Error::Connect(ctx) => write!(f, "failed to connect to endpoint: {ctx}"),
             Error::Hostname(ctx) => write!(f, "failed to recover our hostname: {ctx}"),
+            Error::InvalidHost(host) => write!(f, "not an onion address with a port: {host}"),
             Error::Bootstrap(ctx) => write!(f, "failed to connect to Tor network: {ctx}"),
             Error::NotReady => write!(f, "layer not ready"),
         }
@@ -264,3 +483,69 @@ impl Display for Error {
 }
 
 impl std::error::Error for Error {}
+
+impl NetLayerError for Error {
+    fn is_fatal(&self) -> bool {
+        matches!(self, Error::Bootstrap(_) | Error::Init(_) | Error::NotReady)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use futures::{FutureExt, StreamExt};
+
+    use super::{Error, MAX_REJECTIONS_PER_YIELD, TorLayer, TorLayerConfig, next_matching};
+
+    #[tokio::test]
+    async fn wrong_port_flood() {
+        let rejected = AtomicUsize::new(0);
+        let last_warning = Mutex::new(None);
+
+        let mut requests = futures::stream::repeat(80u16);
+        let res = next_matching(
+            &mut requests,
+            |port| *port == 443,
+            |_| {
+                rejected.fetch_add(1, Ordering::Relaxed);
+            },
+            &last_warning,
+        )
+        .now_or_never();
+
+        assert!(res.is_none());
+        assert_eq!(rejected.load(Ordering::Relaxed), MAX_REJECTIONS_PER_YIELD);
+        assert!(last_warning.lock().unwrap().is_some());
+
+        let mut requests =
+            futures::stream::iter(std::iter::repeat_n(80u16, MAX_REJECTIONS_PER_YIELD * 3))
+                .chain(futures::stream::iter([443]));
+        let res = next_matching(&mut requests, |port| *port == 443, |_| {}, &last_warning).await;
+
+        assert_eq!(res, Some(443));
+    }
+
+    #[tokio::test]
+    async fn invalid_nickname() {
+        // would stay pending while bootstrapping if the nickname wasn't checked first
+        let res = TorLayer::new(
+            "not a nickname!".into(),
+            TorLayerConfig::new_from_port(2056),
+        )
+        .now_or_never();
+        assert!(matches!(res, Some(Err(Error::Init(_)))));
+
+        let res = TorLayer::new_for_client("".into()).now_or_never();
+        assert!(matches!(res, Some(Err(Error::Init(_)))));
+
+        let (layer, _) = TorLayer::new_with_progress(
+            "not/a/nickname".into(),
+            TorLayerConfig::new_from_port(2056),
+        );
+        assert!(matches!(layer.now_or_never(), Some(Err(Error::Init(_)))));
+    }
+}
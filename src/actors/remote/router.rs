@@ -6,43 +6,114 @@
 //! The wire protocol is defined as follows:
 //!
 //! ## Message
-//! ` N_id | Id[N_id] | N_m | M[N_m] `
+//! ` V | N_id | Id[N_id] | N_m | M[N_m] | N_k | K[N_k] `
 //!
 //! where
 //!
+//! * `V`: 2 bytes -> `u16`, the client's protocol version (see [`PROTOCOL_VERSION`])
 //! * `N_id`: 2 bytes -> `u16`
 //! * `Id[N_id]`: `N_id` bytes -> `[u8; N_id]`
 //! * `N_m`: 4 bytes -> `u32`
 //! * `M[N_m]`: `N_m` bytes -> `[u8; N_m]`
+//! * `N_k`: 2 bytes -> `u16`, zero if the message has no idempotency key
+//! * `K[N_k]`: `N_k` bytes -> `[u8; N_k]`, the idempotency key
+//!
+//! clients before version 2 send neither `N_k` nor `K`. `N_m` may be zero: an empty body
+//! isn't a hang-up, but it's dropped like any other body the dencoder fails to decode.
 //!
 //! ## Reply
-//! `N_r | R[N_r]`
+//! `S | N_r | R[N_r] | Q`
 //!
 //! where
 //!
+//! * `S`: 1 byte -> `u8`, `0` if the message was relayed
 //! * `N_r`: 4 bytes -> `u32`
 //! * `R[N_r]`: `N_m` bytes -> `[u8; N_r]`
+//! * `Q`: 8 bytes -> `u64`, request ID assigned by the router, for correlating logs
+//!
+//! replies to `Message::Stream` are preceded by any number of chunks, each framed as
+//! `S | N_c | C[N_c]`, with `S` set to `2`, `N_c` being 4 bytes -> `u32` and `C[N_c]` the
+//! chunk's `N_c` bytes, in the order the actor wrote them.
+//!
+//! if the client's version is below the router's minimum (see [`RouterOpts::min_client_version`]),
+//! zero or above [`PROTOCOL_VERSION`], the reply is `S | V_min` instead, with `S` set to `1` and
+//! `V_min` (2 bytes -> `u16`) being the minimum version accepted. clients from before versioning
+//! start with `N_id` and get this reply too.
 //!
 
-use std::{collections::HashMap, fmt::Display, marker::PhantomData, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::Display,
+    marker::PhantomData,
+    panic::AssertUnwindSafe,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
 
-use serde::{Serialize, de::DeserializeOwned};
+use futures::{
+    FutureExt,
+    future::{BoxFuture, Shared},
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
     sync::{RwLock, mpsc, oneshot},
 };
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
 
 use crate::{
-    actors::remote::UntypedHandle,
-    messaging::{Message, MsgResult},
+    actors::remote::{self, UntypedHandle},
+    messaging::{Message, MsgResult, Reply, ReplyWriter, TypeSignature},
+    utils::spawn_task,
 };
 
 use super::{
     address::{self, ActorAddress, PeerId},
     dencoder::{self, Dencoder},
-    netlayer::{AsyncMsgStream, NetLayer},
+    netlayer::{AsyncMsgStream, NetLayer, NetLayerError},
 };
 
+const DEFAULT_READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// wire protocol version spoken by this crate's routers and remote handles
+pub const PROTOCOL_VERSION: u16 = 2;
+
+///
+/// highest [`RouterOpts::max_msg_size`] allowed. the wire could carry up to `u32::MAX` bytes,
+/// but a limit that high would let any peer make the router buffer gigabytes.
+///
+pub const MAX_MSG_SIZE_LIMIT: u32 = 256 * 1024 * 1024;
+
+/// first protocol version sending idempotency keys
+const IDEMPOTENCY_KEY_VERSION: u16 = 2;
+
+const STATUS_OK: u8 = 0;
+const STATUS_VERSION_REJECTED: u8 = 1;
+const STATUS_CHUNK: u8 = 2;
+
+/// chunks of a streamed reply buffered before the actor has to wait for the client
+const STREAM_BUFFER: usize = 16;
+
+/// connections accepted, recorded with the `metrics` feature
+#[cfg(feature = "metrics")]
+pub const METRIC_CONNECTIONS: &str = "myriam_router_connections_total";
+
+/// messages relayed and replied to, recorded with the `metrics` feature
+#[cfg(feature = "metrics")]
+pub const METRIC_RELAYED: &str = "myriam_router_relayed_total";
+
+/// connections dropped without a reply, labeled by `reason`, recorded with the `metrics` feature
+#[cfg(feature = "metrics")]
+pub const METRIC_DROPPED: &str = "myriam_router_dropped_total";
+
+/// time spent by actors handling relayed messages, recorded with the `metrics` feature
+#[cfg(feature = "metrics")]
+pub const METRIC_HANDLER_SECONDS: &str = "myriam_router_handler_seconds";
+
 ///
 /// router for exposing actors under a given net layer
 ///
@@ -54,37 +125,89 @@ impl Router {
     /// spawn a new router event loop using the given net layer, and return a handle to it
     ///
     pub async fn with_netlayer<N>(
-        mut netlayer: N,
+        netlayer: N,
         opts: Option<RouterOpts>,
     ) -> Result<RouterHandle, Error>
+    where
+        N: NetLayer + Send + 'static,
+        <N as NetLayer>::Error: Send + std::fmt::Display,
+    {
+        let (handle, router) = Self::into_future(netlayer, opts).await?;
+        spawn_task("myriam::router", router);
+
+        Ok(handle)
+    }
+
+    ///
+    /// set up a new router using the given net layer like [`Self::with_netlayer()`], but return
+    /// its event loop instead of spawning it, e.g. to run it on a `LocalSet` or in a `select!`.
+    ///
+    /// the handle's requests wait until the loop is polled, and fail once it's dropped.
+    ///
+    pub async fn into_future<N>(
+        mut netlayer: N,
+        opts: Option<RouterOpts>,
+    ) -> Result<(RouterHandle, impl Future<Output = ()> + Send + 'static), Error>
     where
         N: NetLayer + Send + 'static,
         <N as NetLayer>::Error: Send + std::fmt::Display,
     {
         let opts = opts.unwrap_or_default();
+        opts.warn_invalid();
+        let init_timeout = Duration::from_millis(opts.init_timeout());
 
-        netlayer.init().await.map_err(|e| {
-            tracing::error!("router init: {e}");
-            Error::Init(e.to_string())
-        })?;
+        let setup = async {
+            netlayer.init().await.map_err(|e| {
+                tracing::error!("router init: {e}");
+                Error::Init(e.to_string())
+            })?;
 
-        let host_address = netlayer.address().await.map_err(|e| {
-            tracing::error!("router init: failed to obtain address - {e}");
-            Error::Init(e.to_string())
-        })?;
+            if opts.await_ready() {
+                netlayer.ready().await.map_err(|e| {
+                    tracing::error!("router init: net layer not ready - {e}");
+                    Error::Init(e.to_string())
+                })?;
+            }
 
-        let host_address_inner = host_address.clone();
+            let address = netlayer.address().await.map_err(|e| {
+                tracing::error!("router init: failed to obtain address - {e}");
+                Error::Init(e.to_string())
+            })?;
 
-        let peers: HashMap<PeerId, UntypedHandle> = HashMap::new();
+            let public_address = netlayer.public_address().map_err(|e| {
+                tracing::error!("router init: failed to obtain public address - {e}");
+                Error::Init(e.to_string())
+            })?;
 
-        let (sender, mut receiver) =
-            mpsc::channel::<(RouterMessage, oneshot::Sender<Result<RouterReply, Error>>)>(1024);
-        let (conf_sender, conf_receiver) = oneshot::channel::<Result<(), Error>>();
+            Ok((address, public_address))
+        };
 
-        tokio::spawn(async move {
-            let opts = Arc::new(opts);
+        let (host_address, public_address) = tokio::time::timeout(init_timeout, setup)
+            .await
+            .map_err(|_| {
+                tracing::error!("router init: net layer setup timed out");
+                Error::Init("net layer setup timed out".into())
+            })??;
+
+        // what goes in attached actors' addresses, unless overridden by the router's options
+        let host_address_inner = public_address.unwrap_or_else(|| host_address.clone());
+
+        let peers: HashMap<PeerId, Peer> = HashMap::new();
+
+        let (sender, mut receiver) = mpsc::channel::<(
+            RouterMessage,
+            oneshot::Sender<Result<RouterReply, Error>>,
+        )>(opts.channel_capacity().max(1));
+
+        let router = async move {
+            let mut opts = Arc::new(opts);
             let peers = Arc::new(RwLock::new(peers));
-            let _ = conf_sender.send(Ok(()));
+            let counters = Arc::new(Counters::default());
+            let replies = Arc::new(Mutex::new(ReplyCache::default()));
+
+            // consecutive accept failures, and when to try accepting again after the last one
+            let mut accept_failures = 0;
+            let mut accept_resume: Option<tokio::time::Instant> = None;
 
             loop {
                 tokio::select! {
@@ -94,19 +217,33 @@ impl Router {
                                 let _ = sender.send(Ok(RouterReply::Accepted));
                                 return;
                             },
-                            RouterMessage::Attach { handle, peer_id } => {
+                            RouterMessage::Attach { peer, peer_id } => {
+                                let host = opts.advertised_host().unwrap_or(&host_address_inner);
                                 let addr = match peer_id {
-                                    Some(id) => ActorAddress::new_with_peer_id::<N>(&host_address_inner, id),
-                                    None => match ActorAddress::new::<N>(&host_address_inner) {
+                                    Some(id) => ActorAddress::new_with_peer_id::<N>(host, id),
+                                    None => match ActorAddress::new::<N>(host) {
                                         Ok(addr) => addr,
                                         Err(err) => {
                                             tracing::error!("router: attach - {err}");
+                                            let _ = sender.send(Err(Error::Address(err)));
                                             continue;
                                         }
                                     },
                                 };
 
-                                peers.write().await.insert(addr.peer_id().to_owned(), handle);
+                                let mut attached = peers.write().await;
+
+                                // replacing an already attached peer doesn't take a new slot
+                                if let Some(max_peers) = opts.max_peers()
+                                    && attached.len() >= max_peers
+                                    && !attached.contains_key(addr.peer_id())
+                                {
+                                    tracing::warn!("router: attach - at capacity ({max_peers} peers)");
+                                    let _ = sender.send(Err(Error::CapacityReached));
+                                    continue;
+                                }
+
+                                attached.insert(addr.peer_id().to_owned(), peer);
 
                                 let _ = sender.send(Ok(RouterReply::Address(addr)));
                             },
@@ -115,48 +252,276 @@ impl Router {
 
                                 let _ = sender.send(Ok(RouterReply::Address(addr)));
                             },
+                            RouterMessage::UpdateOpts(new_opts) => {
+                                // these are only read while starting the router
+                                if let Some(field) = opts.startup_change(&new_opts) {
+                                    let _ = sender.send(Err(Error::InvalidOpts(format!(
+                                        "{field} can't be changed on a running router"
+                                    ))));
+                                    continue;
+                                }
+
+                                // connections already accepted keep their own snapshot
+                                new_opts.warn_invalid();
+                                opts = Arc::new(new_opts);
+
+                                let _ = sender.send(Ok(RouterReply::Accepted));
+                            },
+                            RouterMessage::ExportState => {
+                                let host = opts.advertised_host().unwrap_or(&host_address_inner);
+                                let mut peers: Vec<ActorAddress> = peers
+                                    .read()
+                                    .await
+                                    .keys()
+                                    .map(|id| ActorAddress::new_with_peer_id::<N>(host, id.clone()))
+                                    .collect();
+
+                                peers.sort_by(|a, b| a.peer_id().bytes().cmp(b.peer_id().bytes()));
+
+                                let _ = sender.send(Ok(RouterReply::State(RouterState { peers })));
+                            },
+                            RouterMessage::Peers => {
+                                // lazy peers nobody requested yet are left alone
+                                let peers = peers
+                                    .read()
+                                    .await
+                                    .iter()
+                                    .filter_map(|(id, peer)| Some((id.clone(), peer.spawned()?)))
+                                    .collect();
+
+                                let _ = sender.send(Ok(RouterReply::Peers(peers)));
+                            },
+                            RouterMessage::Metrics => {
+                                let metrics = RouterMetrics {
+                                    attached: peers.read().await.len(),
+                                    connections: counters.connections.load(Ordering::Relaxed),
+                                    relayed: counters.relayed.load(Ordering::Relaxed),
+                                    dropped: counters.dropped.load(Ordering::Relaxed),
+                                };
+
+                                let _ = sender.send(Ok(RouterReply::Metrics(metrics)));
+                            },
                         }
                     },
-                    Ok(mut stream) = netlayer.accept() => {
+                    _ = tokio::time::sleep_until(accept_resume.unwrap_or_else(tokio::time::Instant::now)), if accept_resume.is_some() => {
+                        accept_resume = None;
+                    },
+                    res = netlayer.accept(), if accept_resume.is_none() => {
+                        let mut stream = match res {
+                            Ok(stream) => {
+                                accept_failures = 0;
+                                stream
+                            },
+                            Err(err) if err.is_fatal() => {
+                                tracing::error!("router: accept - {err}; stopping");
+                                return;
+                            },
+                            Err(err) => {
+                                accept_failures += 1;
+                                let backoff = opts.accept_backoff_after(accept_failures);
+                                tracing::warn!("router: accept - {err}; retrying in {backoff:?}");
+
+                                accept_resume = Some(tokio::time::Instant::now() + backoff);
+                                continue;
+                            },
+                        };
+
                         let opts = opts.clone();
                         let peers = peers.clone();
+                        let counters = counters.clone();
+                        let replies = replies.clone();
+
+                        let request_id = counters.connections.fetch_add(1, Ordering::Relaxed) + 1;
+                        #[cfg(feature = "metrics")]
+                        metrics::counter!(METRIC_CONNECTIONS).increment(1);
+                        let span = tracing::info_span!("request", request_id, label = tracing::field::Empty);
+
+                        spawn_task("myriam::router::connection", async move {
+                            // label of the handle the request is for, once known
+                            #[cfg(feature = "metrics")]
+                            let label = std::sync::OnceLock::<Arc<str>>::new();
+                            #[cfg(feature = "metrics")]
+                            let label_ref = &label;
 
-                        tokio::spawn(async move {
-                            let _ = tokio::time::timeout(
+                            let res = tokio::time::timeout(
                                 Duration::from_millis(opts.msg_read_timeout()),
                                 async move {
-                                    let id = match try_read_id(&mut stream).await {
-                                        Ok(id) => id,
-                                        Err(_) => {
-                                            return;
-                                        },
-                                    };
+                                    let version = try_check_version(&mut stream, opts.min_client_version()).await?;
 
-                                    let handle = match peers.read().await.get(&id) {
-                                        Some(handle) => handle.clone(),
+                                    let id = try_read_id(&mut stream).await?;
+
+                                    let peer = peers.read().await.get(&id).cloned();
+                                    let handle = match peer {
+                                        Some(peer) => peer.handle().await?,
                                         None => {
-                                            tracing::warn!("router: recv - unknown peer {id}");
-                                            return;
+                                            tracing::warn!("router: recv - unknown peer {}", id.redacted());
+                                            return Err(Error::Recv("unknown peer".into()));
                                         },
                                     };
 
-                                    let _ = try_handle_message(stream, handle, opts.as_ref()).await;
+                                    if let Some(handle_label) = handle.label() {
+                                        tracing::Span::current().record("label", handle_label);
+                                        #[cfg(feature = "metrics")]
+                                        let _ = label_ref.set(handle_label.into());
+                                    }
+
+                                    tracing::debug!("router: relaying to {}", id.redacted());
+
+                                    let request = Request { id, version, request_id };
+                                    try_handle_message(stream, handle, opts.as_ref(), &request, &replies).await
                                 }).await;
-                        });
+
+                            #[cfg(feature = "metrics")]
+                            {
+                                let label = label.get().map(|label| label.to_string()).unwrap_or_default();
+
+                                match &res {
+                                    Ok(Ok(())) => metrics::counter!(METRIC_RELAYED).increment(1),
+                                    Ok(Err(err)) => metrics::counter!(METRIC_DROPPED, "reason" => drop_reason(err), "label" => label).increment(1),
+                                    Err(_) => metrics::counter!(METRIC_DROPPED, "reason" => "timeout", "label" => label).increment(1),
+                                }
+                            }
+
+                            match res {
+                                Ok(Ok(())) => counters.relayed.fetch_add(1, Ordering::Relaxed),
+                                _ => counters.dropped.fetch_add(1, Ordering::Relaxed),
+                            };
+                        }.instrument(span));
                     }
                 }
             }
-        });
-
-        conf_receiver
-            .await
-            .map_err(|e| Error::Init(e.to_string()))??;
+        };
 
-        Ok(RouterHandle {
+        let handle = RouterHandle {
             sender,
             host_address,
-        })
+            proto_name: N::name(),
+        };
+
+        Ok((handle, router))
+    }
+
+    ///
+    /// spawn a new router like [`Self::with_netlayer()`], re-attaching every peer in `state`
+    /// (see [`RouterHandle::export_state()`]) to its handle in `handles`, under the same peer ID.
+    ///
+    /// fails with [`Error::MissingHandles`] before spawning anything if some peer has no handle,
+    /// or [`Error::DuplicatePeers`] if `state` lists some peer more than once.
+    /// handles for peers not in `state` are ignored.
+    ///
+    pub async fn restore<N>(
+        netlayer: N,
+        opts: Option<RouterOpts>,
+        state: RouterState,
+        mut handles: HashMap<PeerId, UntypedHandle>,
+    ) -> Result<RouterHandle, Error>
+    where
+        N: NetLayer + Send + 'static,
+        <N as NetLayer>::Error: Send + std::fmt::Display,
+    {
+        let missing: Vec<PeerId> = state
+            .peers
+            .iter()
+            .map(|addr| addr.peer_id())
+            .filter(|id| !handles.contains_key(id))
+            .cloned()
+            .collect();
+
+        if !missing.is_empty() {
+            tracing::error!("router restore: {} peers have no handle", missing.len());
+            return Err(Error::MissingHandles(missing));
+        }
+
+        let (mut seen, mut reported) = (HashSet::new(), HashSet::new());
+        let duplicates: Vec<PeerId> = state
+            .peers
+            .iter()
+            .map(|addr| addr.peer_id())
+            .filter(|id| !seen.insert(*id) && reported.insert(*id))
+            .cloned()
+            .collect();
+
+        if !duplicates.is_empty() {
+            tracing::error!(
+                "router restore: {} peers listed more than once",
+                duplicates.len()
+            );
+            return Err(Error::DuplicatePeers(duplicates));
+        }
+
+        let router = Self::with_netlayer(netlayer, opts).await?;
+
+        for addr in state.peers {
+            let Some(handle) = handles.remove(addr.peer_id()) else {
+                // checked above, but don't leave a half-restored router running regardless
+                let _ = router.stop().await;
+                return Err(Error::MissingHandles(vec![addr.peer_id().clone()]));
+            };
+
+            if let Err(err) = router.attach_with_id(handle, addr.peer_id().clone()).await {
+                tracing::error!(
+                    "router restore: failed to re-attach {} - {err}",
+                    addr.peer_id().redacted()
+                );
+                let _ = router.stop().await;
+
+                return Err(err);
+            }
+        }
+
+        if !handles.is_empty() {
+            tracing::warn!(
+                "router restore: ignoring {} handles not in state",
+                handles.len()
+            );
+        }
+
+        Ok(router)
+    }
+}
+
+async fn try_check_version<S>(stream: &mut S, min_version: u16) -> Result<u16, Error>
+where
+    S: AsyncMsgStream,
+{
+    let version = stream.read_u16().await.map_err(|e| {
+        tracing::error!("router: could not read protocol version - {e}");
+        Error::Recv(e.to_string())
+    })?;
+
+    // clients from before versioning send their peer ID's length first, never a known version
+    let min_version = min_version.max(1);
+    if version > PROTOCOL_VERSION {
+        tracing::warn!(
+            "router: rejecting client with unknown protocol version {version} (maybe unversioned)"
+        );
+    } else if version >= min_version {
+        return Ok(version);
+    } else {
+        tracing::warn!(
+            "router: rejecting client with protocol version {version} (minimum is {min_version})"
+        );
     }
+
+    stream
+        .write_u8(STATUS_VERSION_REJECTED)
+        .await
+        .map_err(|err| {
+            tracing::error!("router: could not send rejection - {err}");
+            Error::Send(err.to_string())
+        })?;
+
+    stream.write_u16(min_version).await.map_err(|err| {
+        tracing::error!("router: could not send minimum version - {err}");
+        Error::Send(err.to_string())
+    })?;
+
+    stream.flush().await.map_err(|err| {
+        tracing::error!("router: could not flush rejection - {err}");
+        Error::Send(err.to_string())
+    })?;
+
+    Err(Error::VersionRejected(min_version))
 }
 
 async fn try_read_id<S>(stream: &mut S) -> Result<PeerId, Error>
@@ -177,32 +542,213 @@ where
     Ok(PeerId::new_from_bytes(&id_buffer))
 }
 
+///
+/// read `size` bytes from `stream`, growing the buffer at most `chunk_size` bytes at a time
+/// so a peer can't make us allocate more than what it actually sends
+///
+async fn read_chunked<S>(stream: &mut S, size: usize, chunk_size: usize) -> std::io::Result<Vec<u8>>
+where
+    S: AsyncReadExt + Unpin,
+{
+    let chunk_size = chunk_size.max(1);
+    let mut buffer = Vec::with_capacity(size.min(chunk_size));
+
+    while buffer.len() < size {
+        let start = buffer.len();
+        let end = size.min(start + chunk_size);
+
+        buffer.resize(end, 0);
+        stream.read_exact(&mut buffer[start..end]).await?;
+    }
+
+    Ok(buffer)
+}
+
+async fn try_read_key<S>(stream: &mut S) -> Result<Vec<u8>, Error>
+where
+    S: AsyncReadExt + Unpin,
+{
+    let size = stream.read_u16().await.map_err(|e| {
+        tracing::error!("router: recv - could not read idempotency key size - {e}");
+        Error::Recv(e.to_string())
+    })?;
+
+    let mut key = vec![0; size as usize];
+    stream.read_exact(&mut key).await.map_err(|e| {
+        tracing::error!("router: recv - could not read idempotency key - {e}");
+        Error::Recv(e.to_string())
+    })?;
+
+    Ok(key)
+}
+
+/// what the router knows about an incoming request once past its header
+struct Request {
+    id: PeerId,
+    version: u16,
+    request_id: u64,
+}
+
 async fn try_handle_message<S>(
     mut stream: S,
     handle: UntypedHandle,
     opts: &RouterOpts,
+    request: &Request,
+    replies: &Mutex<ReplyCache>,
 ) -> Result<(), Error>
 where
     S: AsyncMsgStream,
 {
-    let msg_size = stream.read_u32().await.map_err(|e| {
-        tracing::error!("router: recv - could not read msg size - {e}");
-        Error::Recv(e.to_string())
-    })?;
+    let msg_size = match stream.read_u32().await {
+        Ok(size) => size,
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+            tracing::debug!("router: client disconnected before sending a message");
+            Err(Error::Disconnected)?
+        }
+        Err(e) => {
+            tracing::error!("router: recv - could not read msg size - {e}");
+            Err(Error::Recv(e.to_string()))?
+        }
+    };
 
     if msg_size > opts.max_msg_size() {
         tracing::warn!("router: recv - incoming message body exceeds size limit; dropping");
         Err(Error::Recv("message too big".into()))?
     }
 
-    let mut msg_buffer = vec![0; msg_size as usize];
-    stream.read_exact(&mut msg_buffer).await.map_err(|e| {
-        tracing::error!("router: recv - could not read msg - {e}");
-        Error::Recv(e.to_string())
-    })?;
+    let msg_buffer = read_chunked(&mut stream, msg_size as usize, opts.read_chunk_size())
+        .await
+        .map_err(|e| {
+            tracing::error!("router: recv - could not read msg - {e}");
+            Error::Recv(e.to_string())
+        })?;
+
+    let key = match request.version >= IDEMPOTENCY_KEY_VERSION {
+        true => try_read_key(&mut stream).await?,
+        false => vec![],
+    };
+
+    let key = (!key.is_empty()).then(|| (request.id.clone(), key));
+    let ttl = Duration::from_millis(opts.idempotency_ttl());
+    let capacity = opts.idempotency_cache_size();
+
+    // the first request with a key runs, repeats replay its reply once it's there
+    let mut claimed = None;
+    if let Some(key) = key {
+        loop {
+            let claim = replies
+                .lock()
+                .expect("poisoned lock")
+                .claim(&key, ttl, capacity);
+
+            match claim {
+                Claim::Replay(sent) => {
+                    tracing::debug!("router: repeated idempotency key, replaying cached reply");
+                    return try_replay(&mut stream, &sent, request.request_id).await;
+                }
+                Claim::Wait(pending) => {
+                    tracing::debug!(
+                        "router: repeated idempotency key, waiting for the first reply"
+                    );
+                    if let Ok(sent) = pending.await {
+                        return try_replay(&mut stream, &sent, request.request_id).await;
+                    }
+
+                    // the first request failed without a reply, so this one gets to run
+                }
+                Claim::Run { claim, sender } => {
+                    claimed = Some(Claimed {
+                        replies,
+                        key: key.clone(),
+                        claim,
+                        sender: Some(sender),
+                        chunks: vec![],
+                    });
+                    break;
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    let started = std::time::Instant::now();
+
+    let (writer, mut chunks) = ReplyWriter::channel(STREAM_BUFFER);
+    let relay = handle.send_streaming(msg_buffer, writer);
+    tokio::pin!(relay);
+
+    let sent = loop {
+        tokio::select! {
+            biased;
+            Some(chunk) = chunks.recv() => {
+                try_write_chunk(&mut stream, &chunk).await?;
+                if let Some(claimed) = &mut claimed {
+                    claimed.chunks.push(chunk);
+                }
+            },
+            sent = &mut relay => break sent,
+        }
+    };
+
+    #[cfg(feature = "metrics")]
+    metrics::histogram!(METRIC_HANDLER_SECONDS).record(started.elapsed().as_secs_f64());
+
+    // the handler is done, send whatever it wrote last
+    while let Ok(chunk) = chunks.try_recv() {
+        try_write_chunk(&mut stream, &chunk).await?;
+        if let Some(claimed) = &mut claimed {
+            claimed.chunks.push(chunk);
+        }
+    }
+
+    // busy or failed requests aren't cached, dropping the claim lets a repeat run instead
+    let res = match sent {
+        Ok(res) => {
+            if let Some(claimed) = claimed {
+                claimed.complete(res.clone(), ttl, capacity);
+            }
+
+            res
+        }
+        Err(remote::Error::Busy) => match handle.busy_reply() {
+            Some(reply) => {
+                tracing::warn!("router: actor busy, asking client to try again later");
+                reply.to_vec()
+            }
+            None => {
+                tracing::error!("router: actor busy and no busy reply to send");
+                Err(Error::Send(remote::Error::Busy.to_string()))?
+            }
+        },
+        Err(err) => {
+            tracing::error!("router: msg error - {err}");
+            Err(Error::Send(err.to_string()))?
+        }
+    };
+
+    try_write_reply(&mut stream, &res, request.request_id).await
+}
+
+///
+/// send a cached reply again, streamed chunks first
+///
+async fn try_replay<S>(stream: &mut S, sent: &SentReply, request_id: u64) -> Result<(), Error>
+where
+    S: AsyncMsgStream,
+{
+    for chunk in &sent.chunks {
+        try_write_chunk(stream, chunk).await?;
+    }
 
-    let res = handle.send(msg_buffer).await.map_err(|err| {
-        tracing::error!("router: msg error - {err}");
+    try_write_reply(stream, &sent.reply, request_id).await
+}
+
+async fn try_write_reply<S>(stream: &mut S, res: &[u8], request_id: u64) -> Result<(), Error>
+where
+    S: AsyncMsgStream,
+{
+    stream.write_u8(STATUS_OK).await.map_err(|err| {
+        tracing::error!("router: could not send response status - {err}");
         Error::Send(err.to_string())
     })?;
 
@@ -211,17 +757,45 @@ where
         Error::Send(err.to_string())
     })?;
 
-    stream.write_all(&res).await.map_err(|err| {
+    stream.write_all(res).await.map_err(|err| {
         tracing::error!("router: could not send response - {err}");
         Error::Send(err.to_string())
     })?;
 
+    stream.write_u64(request_id).await.map_err(|err| {
+        tracing::error!("router: could not send request ID - {err}");
+        Error::Send(err.to_string())
+    })?;
+
     stream.flush().await.map_err(|err| {
         tracing::error!("router: could not flush response - {err}");
         Error::Send(err.to_string())
+    })
+}
+
+async fn try_write_chunk<S>(stream: &mut S, chunk: &[u8]) -> Result<(), Error>
+where
+    S: AsyncMsgStream,
+{
+    stream.write_u8(STATUS_CHUNK).await.map_err(|err| {
+        tracing::error!("router: could not send chunk status - {err}");
+        Error::Send(err.to_string())
+    })?;
+
+    stream.write_u32(chunk.len() as u32).await.map_err(|err| {
+        tracing::error!("router: could not send chunk size - {err}");
+        Error::Send(err.to_string())
+    })?;
+
+    stream.write_all(chunk).await.map_err(|err| {
+        tracing::error!("router: could not send chunk - {err}");
+        Error::Send(err.to_string())
     })?;
 
-    Ok(())
+    stream.flush().await.map_err(|err| {
+        tracing::error!("router: could not flush chunk - {err}");
+        Error::Send(err.to_string())
+    })
 }
 
 ///
@@ -237,38 +811,363 @@ pub struct RouterOpts {
     pub msg_read_timeout: u64,
 
     ///
-    /// timeout in milliseconds for reading messages from the net layer's stream.
+    /// max size in bytes of incoming message bodies. bigger messages are dropped unread.
     ///
-    /// default is 5000.
+    /// must be between 1 and [`MAX_MSG_SIZE_LIMIT`]. default is 4194304.
     ///
     pub max_msg_size: u32,
-}
 
-impl RouterOpts {
-    /// create a new set of router options
-    pub fn new(msg_read_timeout: u64, max_msg_size: u32) -> Self {
-        Self {
-            msg_read_timeout,
-            max_msg_size,
-        }
-    }
+    ///
+    /// whether to wait for the net layer to be ready (see [`NetLayer::ready()`]) before
+    /// starting the router.
+    ///
+    /// default is true.
+    ///
+    pub await_ready: bool,
 
-    /// get the message read timeout
-    pub fn msg_read_timeout(&self) -> u64 {
-        self.msg_read_timeout
-    }
+    ///
+    /// size in bytes of the increments in which message bodies are read from the stream.
+    ///
+    /// default is 65536.
+    ///
+    pub read_chunk_size: usize,
 
-    /// get the max message size.
-    pub fn max_msg_size(&self) -> u32 {
-        self.max_msg_size
-    }
-}
+    ///
+    /// host to put in attached actors' addresses instead of the net layer's own address,
+    /// e.g. when the layer binds to an address remote peers can't dial.
+    ///
+    /// default is none.
+    ///
+    pub advertised_host: Option<String>,
 
-impl Default for RouterOpts {
-    fn default() -> Self {
-        Self {
+    ///
+    /// lowest protocol version (see [`PROTOCOL_VERSION`]) accepted from clients.
+    /// older clients get a rejection reply instead of being relayed, and so do clients
+    /// claiming 0 or a version above [`PROTOCOL_VERSION`].
+    ///
+    /// default is 0, accepting any known version.
+    ///
+    pub min_client_version: u16,
+
+    ///
+    /// how many commands (attach, revoke, etc) can be queued for the router.
+    ///
+    /// default is 1024.
+    ///
+    pub channel_capacity: usize,
+
+    ///
+    /// maximum number of actors attached at once. attaching beyond it fails with
+    /// [`Error::CapacityReached`] until something is revoked.
+    ///
+    /// default is none, for no limit.
+    ///
+    pub max_peers: Option<usize>,
+
+    ///
+    /// timeout in milliseconds for setting up the net layer (init, readiness and address)
+    /// and starting the router.
+    ///
+    /// default is 120000.
+    ///
+    pub init_timeout: u64,
+
+    ///
+    /// how many replies to requests with an idempotency key are kept, across peers, for
+    /// replaying to repeated requests. the oldest ones are evicted first.
+    ///
+    /// default is 1024. zero disables the cache.
+    ///
+    pub idempotency_cache_size: usize,
+
+    ///
+    /// time in milliseconds a reply to a request with an idempotency key is replayed for.
+    ///
+    /// default is 60000.
+    ///
+    pub idempotency_ttl: u64,
+
+    ///
+    /// time in milliseconds to wait before accepting again after a failed accept, doubling with
+    /// each consecutive failure up to [`Self::max_accept_backoff`]. commands are still handled
+    /// while waiting.
+    ///
+    /// default is 10. zero retries right away.
+    ///
+    pub accept_backoff: u64,
+
+    ///
+    /// upper bound in milliseconds for the wait between failed accepts.
+    ///
+    /// default is 1000.
+    ///
+    pub max_accept_backoff: u64,
+}
+
+impl RouterOpts {
+    ///
+    /// start building a set of router options, with defaults for anything left unset
+    ///
+    pub fn builder() -> RouterOptsBuilder {
+        RouterOptsBuilder {
+            opts: Self::default(),
+        }
+    }
+
+    ///
+    /// create a new set of router options.
+    ///
+    /// `max_msg_size` is clamped between 1 and [`MAX_MSG_SIZE_LIMIT`], with a warning.
+    ///
+    pub fn new(msg_read_timeout: u64, max_msg_size: u32) -> Self {
+        let clamped = max_msg_size.clamp(1, MAX_MSG_SIZE_LIMIT);
+        if clamped != max_msg_size {
+            tracing::warn!(
+                "router opts: max_msg_size {max_msg_size} out of bounds, using {clamped}"
+            );
+        }
+
+        Self {
+            msg_read_timeout,
+            max_msg_size: clamped,
+            ..Default::default()
+        }
+    }
+
+    /// options set directly on the struct skip validation, so at least complain about them
+    fn warn_invalid(&self) {
+        if self.max_msg_size == 0 {
+            tracing::warn!("router opts: max_msg_size is zero, every message will be dropped");
+        } else if self.max_msg_size > MAX_MSG_SIZE_LIMIT {
+            tracing::warn!(
+                "router opts: max_msg_size {} above {MAX_MSG_SIZE_LIMIT}, peers can make the router buffer that much",
+                self.max_msg_size
+            );
+        }
+    }
+
+    /// get the message read timeout
+    pub fn msg_read_timeout(&self) -> u64 {
+        self.msg_read_timeout
+    }
+
+    /// get the max message size.
+    pub fn max_msg_size(&self) -> u32 {
+        self.max_msg_size
+    }
+
+    /// get whether the router waits for its net layer to be ready
+    pub fn await_ready(&self) -> bool {
+        self.await_ready
+    }
+
+    /// get the read chunk size
+    pub fn read_chunk_size(&self) -> usize {
+        self.read_chunk_size
+    }
+
+    /// get the advertised host override, if any
+    pub fn advertised_host(&self) -> Option<&str> {
+        self.advertised_host.as_deref()
+    }
+
+    /// get the minimum accepted client protocol version
+    pub fn min_client_version(&self) -> u16 {
+        self.min_client_version
+    }
+
+    /// get the router's command channel capacity
+    pub fn channel_capacity(&self) -> usize {
+        self.channel_capacity
+    }
+
+    /// get the maximum number of attached actors, if any
+    pub fn max_peers(&self) -> Option<usize> {
+        self.max_peers
+    }
+
+    /// get the router setup timeout
+    pub fn init_timeout(&self) -> u64 {
+        self.init_timeout
+    }
+
+    /// get the number of replies kept for idempotency keys
+    pub fn idempotency_cache_size(&self) -> usize {
+        self.idempotency_cache_size
+    }
+
+    /// get how long replies are kept for idempotency keys
+    pub fn idempotency_ttl(&self) -> u64 {
+        self.idempotency_ttl
+    }
+
+    /// get the initial wait after a failed accept
+    pub fn accept_backoff(&self) -> u64 {
+        self.accept_backoff
+    }
+
+    /// get the maximum wait after a failed accept
+    pub fn max_accept_backoff(&self) -> u64 {
+        self.max_accept_backoff
+    }
+
+    /// wait before accepting again after this many consecutive failures
+    fn accept_backoff_after(&self, failures: u32) -> Duration {
+        let backoff = self
+            .accept_backoff
+            .saturating_mul(1 << failures.saturating_sub(1).min(32))
+            .min(self.max_accept_backoff);
+
+        Duration::from_millis(backoff)
+    }
+
+    /// the first option that only applies on startup and differs from `other`
+    fn startup_change(&self, other: &RouterOpts) -> Option<&'static str> {
+        if self.channel_capacity != other.channel_capacity {
+            Some("channel_capacity")
+        } else if self.init_timeout != other.init_timeout {
+            Some("init_timeout")
+        } else if self.await_ready != other.await_ready {
+            Some("await_ready")
+        } else {
+            None
+        }
+    }
+}
+
+///
+/// fluent builder for [`RouterOpts`]. see [`RouterOpts::builder()`].
+///
+#[derive(Debug)]
+pub struct RouterOptsBuilder {
+    opts: RouterOpts,
+}
+
+impl RouterOptsBuilder {
+    /// timeout in milliseconds for reading messages
+    pub fn read_timeout(mut self, msg_read_timeout: u64) -> Self {
+        self.opts.msg_read_timeout = msg_read_timeout;
+        self
+    }
+
+    /// max size in bytes of incoming message bodies. must be between 1 and [`MAX_MSG_SIZE_LIMIT`]
+    pub fn max_msg_size(mut self, max_msg_size: u32) -> Self {
+        self.opts.max_msg_size = max_msg_size;
+        self
+    }
+
+    /// whether to wait for the net layer to be ready
+    pub fn await_ready(mut self, await_ready: bool) -> Self {
+        self.opts.await_ready = await_ready;
+        self
+    }
+
+    /// size in bytes of the increments in which message bodies are read. must not be zero
+    pub fn read_chunk_size(mut self, read_chunk_size: usize) -> Self {
+        self.opts.read_chunk_size = read_chunk_size;
+        self
+    }
+
+    /// host to advertise in attached actors' addresses
+    pub fn advertised_host(mut self, advertised_host: impl Into<String>) -> Self {
+        self.opts.advertised_host = Some(advertised_host.into());
+        self
+    }
+
+    /// lowest protocol version accepted from clients
+    pub fn min_client_version(mut self, min_client_version: u16) -> Self {
+        self.opts.min_client_version = min_client_version;
+        self
+    }
+
+    /// how many commands can be queued for the router. must not be zero
+    pub fn channel_capacity(mut self, channel_capacity: usize) -> Self {
+        self.opts.channel_capacity = channel_capacity;
+        self
+    }
+
+    /// maximum number of actors attached at once
+    pub fn max_peers(mut self, max_peers: usize) -> Self {
+        self.opts.max_peers = Some(max_peers);
+        self
+    }
+
+    /// timeout in milliseconds for setting up the router
+    pub fn init_timeout(mut self, init_timeout: u64) -> Self {
+        self.opts.init_timeout = init_timeout;
+        self
+    }
+
+    /// number of replies kept for idempotency keys. zero disables the cache
+    pub fn idempotency_cache_size(mut self, idempotency_cache_size: usize) -> Self {
+        self.opts.idempotency_cache_size = idempotency_cache_size;
+        self
+    }
+
+    /// time in milliseconds replies are kept for idempotency keys
+    pub fn idempotency_ttl(mut self, idempotency_ttl: u64) -> Self {
+        self.opts.idempotency_ttl = idempotency_ttl;
+        self
+    }
+
+    /// initial wait in milliseconds after a failed accept
+    pub fn accept_backoff(mut self, accept_backoff: u64) -> Self {
+        self.opts.accept_backoff = accept_backoff;
+        self
+    }
+
+    /// maximum wait in milliseconds after a failed accept
+    pub fn max_accept_backoff(mut self, max_accept_backoff: u64) -> Self {
+        self.opts.max_accept_backoff = max_accept_backoff;
+        self
+    }
+
+    ///
+    /// validate and return the resulting options
+    ///
+    pub fn build(self) -> Result<RouterOpts, Error> {
+        if self.opts.max_msg_size == 0 {
+            return Err(Error::InvalidOpts("max_msg_size must not be zero".into()));
+        }
+
+        if self.opts.max_msg_size > MAX_MSG_SIZE_LIMIT {
+            return Err(Error::InvalidOpts(format!(
+                "max_msg_size must not exceed {MAX_MSG_SIZE_LIMIT}"
+            )));
+        }
+
+        if self.opts.read_chunk_size == 0 {
+            return Err(Error::InvalidOpts(
+                "read_chunk_size must not be zero".into(),
+            ));
+        }
+
+        if self.opts.channel_capacity == 0 {
+            return Err(Error::InvalidOpts(
+                "channel_capacity must not be zero".into(),
+            ));
+        }
+
+        Ok(self.opts)
+    }
+}
+
+impl Default for RouterOpts {
+    fn default() -> Self {
+        Self {
             msg_read_timeout: 5000,
             max_msg_size: 4194304,
+            await_ready: true,
+            read_chunk_size: DEFAULT_READ_CHUNK_SIZE,
+            advertised_host: None,
+            min_client_version: 0,
+            channel_capacity: 1024,
+            max_peers: None,
+            init_timeout: 120_000,
+            idempotency_cache_size: 1024,
+            idempotency_ttl: 60_000,
+            accept_backoff: 10,
+            max_accept_backoff: 1000,
         }
     }
 }
@@ -279,6 +1178,7 @@ impl Default for RouterOpts {
 #[derive(Debug, Clone)]
 pub struct RouterHandle {
     host_address: String,
+    proto_name: &'static str,
     sender: mpsc::Sender<(RouterMessage, oneshot::Sender<Result<RouterReply, Error>>)>,
 }
 
@@ -292,6 +1192,46 @@ impl RouterHandle {
         self.attach_handle(handle, None).await
     }
 
+    ///
+    /// register an actor that isn't spawned yet, getting a new address for it.
+    ///
+    /// `factory` runs on the first request for this address and its handle is kept for the
+    /// following ones. concurrent first requests wait for the same run. if `factory` panics,
+    /// requests for this address fail instead.
+    ///
+    /// actors not spawned yet are skipped by [`Self::broadcast()`].
+    ///
+    pub async fn attach_lazy<F, Fut>(&self, factory: F) -> Result<ActorAddress, Error>
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = UntypedHandle> + Send + 'static,
+    {
+        let spawned = async move {
+            AssertUnwindSafe(factory())
+                .catch_unwind()
+                .await
+                .inspect_err(|_| tracing::error!("router: lazy actor factory panicked"))
+                .ok()
+        }
+        .boxed()
+        .shared();
+
+        self.attach_peer(Peer::Lazy(spawned), None).await
+    }
+
+    ///
+    /// register an actor like [`Self::attach()`], labelling it in the router's logs and metrics.
+    ///
+    /// shorthand for [`UntypedHandle::with_label()`], for telling apart actors sharing a router.
+    ///
+    pub async fn attach_with_label(
+        &self,
+        handle: UntypedHandle,
+        label: impl Into<String>,
+    ) -> Result<ActorAddress, Error> {
+        self.attach_handle(handle.with_label(label), None).await
+    }
+
     ///
     /// register an actor with a given PeerId, getting a new address for it.
     ///
@@ -312,23 +1252,20 @@ impl RouterHandle {
         handle: UntypedHandle,
         peer_id: Option<PeerId>,
     ) -> Result<ActorAddress, Error> {
-        let (sender, receiver) = oneshot::channel();
-        self.sender
-            .send((RouterMessage::Attach { handle, peer_id }, sender))
-            .await
-            .map_err(|e| {
-                tracing::error!("router: {e}");
-                Error::Send(e.to_string())
-            })?;
-
-        let reply = receiver.await.map_err(|e| {
-            tracing::error!("router: {e}");
-            Error::Recv(e.to_string())
-        })??;
+        self.attach_peer(Peer::Ready(handle), peer_id).await
+    }
 
-        match reply {
-            RouterReply::Accepted => panic!("expected Address variant"),
+    async fn attach_peer(
+        &self,
+        peer: Peer,
+        peer_id: Option<PeerId>,
+    ) -> Result<ActorAddress, Error> {
+        match self
+            .request(RouterMessage::Attach { peer, peer_id })
+            .await?
+        {
             RouterReply::Address(a) => Ok(a),
+            other => Err(unexpected_reply("Address", other)),
         }
     }
 
@@ -336,24 +1273,23 @@ impl RouterHandle {
     /// revoke this address. any further requests will be dropped.
     ///
     pub async fn revoke(&self, address: &ActorAddress) -> Result<ActorAddress, Error> {
-        let (sender, receiver) = oneshot::channel();
-        self.sender
-            .send((RouterMessage::Revoke(address.clone()), sender))
-            .await
-            .map_err(|e| {
-                tracing::error!("router: {e}");
-
-                Error::Send(e.to_string())
-            })?;
-
-        let reply = receiver.await.map_err(|e| {
-            tracing::error!("router: {e}");
-            Error::Recv(e.to_string())
-        })??;
-
-        match reply {
-            RouterReply::Accepted => panic!("expected Address variant"),
+        match self.request(RouterMessage::Revoke(address.clone())).await? {
             RouterReply::Address(a) => Ok(a),
+            other => Err(unexpected_reply("Address", other)),
+        }
+    }
+
+    ///
+    /// replace this router's options.
+    ///
+    /// only connections accepted after this call will see the new options. `channel_capacity`,
+    /// `init_timeout` and `await_ready` only apply when starting the router, so changing
+    /// them fails with [`Error::InvalidOpts`].
+    ///
+    pub async fn update_opts(&self, opts: RouterOpts) -> Result<(), Error> {
+        match self.request(RouterMessage::UpdateOpts(opts)).await? {
+            RouterReply::Accepted => Ok(()),
+            other => Err(unexpected_reply("Accepted", other)),
         }
     }
 
@@ -361,25 +1297,115 @@ impl RouterHandle {
     /// stop this router, dropping all registered addresses.
     ///
     pub async fn stop(&self) -> Result<(), Error> {
-        let (sender, receiver) = oneshot::channel();
-        self.sender
-            .send((RouterMessage::Stop, sender))
-            .await
-            .map_err(|e| {
-                tracing::error!("router: {e}");
+        match self.request(RouterMessage::Stop).await? {
+            RouterReply::Accepted => Ok(()),
+            other => Err(unexpected_reply("Accepted", other)),
+        }
+    }
 
-                Error::Send(e.to_string())
-            })?;
+    ///
+    /// snapshot of this router's attached actors and traffic counters.
+    ///
+    pub async fn metrics(&self) -> Result<RouterMetrics, Error> {
+        match self.request(RouterMessage::Metrics).await? {
+            RouterReply::Metrics(m) => Ok(m),
+            other => Err(unexpected_reply("Metrics", other)),
+        }
+    }
 
-        let reply = receiver.await.map_err(|e| {
-            tracing::error!("router: {e}");
-            Error::Recv(e.to_string())
-        })??;
+    ///
+    /// this router's attached peers, for restoring them elsewhere with [`Router::restore()`].
+    ///
+    /// handles themselves aren't exported, and have to be provided again when restoring.
+    ///
+    pub async fn export_state(&self) -> Result<RouterState, Error> {
+        match self.request(RouterMessage::ExportState).await? {
+            RouterReply::State(state) => Ok(state),
+            other => Err(unexpected_reply("State", other)),
+        }
+    }
 
-        match reply {
-            RouterReply::Accepted => Ok(()),
-            RouterReply::Address(_) => panic!("expected Accepted variant"),
+    ///
+    /// send `msg` to every attached actor, collecting their raw replies ordered by peer ID.
+    /// actors attached with [`Self::attach_lazy()`] are skipped until they're spawned.
+    ///
+    /// actors may reply with different types; see [`ReplyEnvelope::decode_as()`].
+    ///
+    pub async fn broadcast<I, D>(&self, msg: Message<I>) -> Result<Vec<ReplyEnvelope>, Error>
+    where
+        I: Serialize,
+        D: Dencoder,
+    {
+        let msg = D::encode(msg).map_err(Error::Serialize)?;
+
+        let peers = match self.request(RouterMessage::Peers).await? {
+            RouterReply::Peers(peers) => peers,
+            other => Err(unexpected_reply("Peers", other))?,
+        };
+
+        let replies = peers.into_iter().map(|(peer_id, handle)| {
+            let msg = msg.clone();
+            async move {
+                let reply = handle.send(msg).await;
+                ReplyEnvelope { peer_id, reply }
+            }
+        });
+
+        let mut envelopes = futures::future::join_all(replies).await;
+        envelopes.sort_by(|a, b| a.peer_id.bytes().cmp(b.peer_id.bytes()));
+
+        Ok(envelopes)
+    }
+
+    ///
+    /// like [`Self::broadcast()`] for actors all replying with `O` and `E`, decoding every reply.
+    ///
+    /// replies that couldn't be relayed or decoded are handed back as they are, separately.
+    ///
+    pub async fn broadcast_typed<I, O, E, D>(
+        &self,
+        msg: Message<I>,
+    ) -> Result<(HashMap<PeerId, MsgResult<O, E>>, Vec<ReplyEnvelope>), Error>
+    where
+        I: Serialize,
+        O: DeserializeOwned,
+        E: DeserializeOwned,
+        D: Dencoder,
+    {
+        let mut replies = HashMap::new();
+        let mut failed = vec![];
+
+        for envelope in self.broadcast::<I, D>(msg).await? {
+            match envelope.decode_as::<O, E, D>() {
+                Ok(reply) => {
+                    replies.insert(envelope.peer_id, reply);
+                }
+                Err(err) => {
+                    tracing::debug!(
+                        "router: broadcast reply from {} not decoded - {err}",
+                        envelope.peer_id.redacted()
+                    );
+                    failed.push(envelope);
+                }
+            }
         }
+
+        Ok((replies, failed))
+    }
+
+    async fn request(&self, command: RouterMessage) -> Result<RouterReply, Error> {
+        let (sender, receiver) = oneshot::channel();
+
+        // the loop only drops its end once it's done
+        self.sender.send((command, sender)).await.map_err(|_| {
+            tracing::error!("router: already stopped");
+            Error::RouterStopped
+        })?;
+
+        receiver.await.map_err(|_| {
+            tracing::error!("router: stopped before replying");
+            Error::RouterStopped
+        })?
     }
 
     ///
@@ -390,15 +1416,27 @@ impl RouterHandle {
     pub fn host_address(&self) -> &str {
         &self.host_address
     }
+
+    ///
+    /// name of the protocol this router is serving.
+    ///
+    /// equivalent to its net layer's [`NetLayer::name()`]
+    ///
+    pub fn proto_name(&self) -> &'static str {
+        self.proto_name
+    }
 }
 
 ///
 /// handle for messaging a remote actor with a given capability.
 ///
-#[derive(Debug, Clone)]
+/// clones share the same net layer.
+///
+#[derive(Debug)]
 pub struct RemoteHandle<I, O, E, D: Dencoder, N: NetLayer> {
     address: ActorAddress,
-    netlayer: N,
+    netlayer: Arc<N>,
+    auto_redial: bool,
 
     _ipd: PhantomData<I>,
     _opd: PhantomData<O>,
@@ -406,6 +1444,20 @@ pub struct RemoteHandle<I, O, E, D: Dencoder, N: NetLayer> {
     _dpd: PhantomData<D>,
 }
 
+impl<I, O, E, D: Dencoder, N: NetLayer> Clone for RemoteHandle<I, O, E, D, N> {
+    fn clone(&self) -> Self {
+        Self {
+            address: self.address.clone(),
+            netlayer: self.netlayer.clone(),
+            auto_redial: self.auto_redial,
+            _ipd: PhantomData,
+            _opd: PhantomData,
+            _epd: PhantomData,
+            _dpd: PhantomData,
+        }
+    }
+}
+
 impl<I, O, E, D, N> RemoteHandle<I, O, E, D, N>
 where
     I: Serialize + DeserializeOwned,
@@ -417,10 +1469,14 @@ where
     ///
     /// create a new handle from this address and net layer for messaging
     ///
+    /// the address isn't validated, so one this net layer can't dial only fails once we
+    /// connect. see [`Self::try_new()`] for a checked version.
+    ///
     pub fn new(address: &ActorAddress, netlayer: N) -> Self {
         Self {
             address: address.to_owned(),
-            netlayer,
+            netlayer: Arc::new(netlayer),
+            auto_redial: false,
             _ipd: PhantomData,
             _opd: PhantomData,
             _epd: PhantomData,
@@ -429,133 +1485,2707 @@ where
     }
 
     ///
-    /// try to message the actor behind our address
+    /// parse `address` and create a new handle from it and this net layer for messaging
     ///
-    pub async fn send(&self, msg: Message<I>) -> Result<MsgResult<O, E>, Error>
-    where
-        <N as NetLayer>::Error: std::fmt::Display,
-    {
-        let mut stream = self
-            .netlayer
-            .connect(self.address.host())
-            .await
-            .map_err(|err| {
-                tracing::error!("remote handle: failed to connect - {err}");
-                Error::Connect(err.to_string())
+    /// fails if the address is malformed or meant for a different net layer, or its host
+    /// isn't valid for this one (see [`NetLayer::validate_host()`]).
+    ///
+    pub fn from_str_with_layer(address: &str, netlayer: N) -> Result<Self, Error> {
+        let address = ActorAddress::try_parse(address).map_err(Error::Address)?;
+
+        Self::try_new(&address, netlayer)
+    }
+
+    ///
+    /// like [`Self::new()`], but failing if the address is meant for a different net layer,
+    /// or its host isn't valid for this one (see [`NetLayer::validate_host()`]).
+    ///
+    pub fn try_new(address: &ActorAddress, netlayer: N) -> Result<Self, Error> {
+        address.check_proto::<N>().map_err(Error::Address)?;
+        address.check_host::<N>().map_err(Error::Address)?;
+
+        Ok(Self::new(address, netlayer))
+    }
+
+    ///
+    /// whether to re-dial once when a connection turns out to be dead, e.g. because it went
+    /// stale: writing the request fails, or the peer hangs up before replying.
+    ///
+    /// only applies before any reply bytes are read. the request might have reached the actor
+    /// before a hang-up, so prefer [`Self::send_idempotent()`] for anything not safe to repeat.
+    /// off by default.
+    ///
+    pub fn with_auto_redial(mut self, auto_redial: bool) -> Self {
+        self.auto_redial = auto_redial;
+        self
+    }
+
+    ///
+    /// try to message the actor behind our address
+    ///
+    /// connection and transport failures are wrapped in [`Error::WithContext`], carrying our address.
+    ///
+    pub async fn send(&self, msg: Message<I>) -> Result<MsgResult<O, E>, Error>
+    where
+        <N as NetLayer>::Error: std::fmt::Display,
+    {
+        let (res, _) = self.send_with_id(msg).await?;
+
+        Ok(res)
+    }
+
+    ///
+    /// like [`Self::send()`], but also returning the ID the router assigned to this request,
+    /// for matching client and router logs.
+    ///
+    pub async fn send_with_id(&self, msg: Message<I>) -> Result<(MsgResult<O, E>, u64), Error>
+    where
+        <N as NetLayer>::Error: std::fmt::Display,
+    {
+        let bytes = D::encode(msg).map_err(Error::Serialize)?;
+        let reply = self.send_raw(&bytes, &[]).await?;
+
+        Ok((decode_reply::<O, E, D>(reply.body)?, reply.request_id))
+    }
+
+    ///
+    /// like [`Self::send()`], tagging the message with an idempotency key.
+    ///
+    /// for as long as the router keeps replies around (see [`RouterOpts::idempotency_ttl`]),
+    /// repeating a key for the same actor gets back the first reply without running the handler
+    /// again, making retries safe. repeats arriving while the first request is still running wait
+    /// for its reply. a first request that fails without a reply, e.g. on a busy actor, isn't kept.
+    ///
+    pub async fn send_idempotent(
+        &self,
+        msg: Message<I>,
+        key: &[u8],
+    ) -> Result<MsgResult<O, E>, Error>
+    where
+        <N as NetLayer>::Error: std::fmt::Display,
+    {
+        if key.len() > u16::MAX as usize {
+            return Err(Error::Send("idempotency key too long".into()));
+        }
+
+        let bytes = D::encode(msg).map_err(Error::Serialize)?;
+        let reply = self.send_raw(&bytes, key).await?;
+
+        decode_reply::<O, E, D>(reply.body)
+    }
+
+    ///
+    /// send `input` as a [`Message::Stream`], returning the actor's reply along with
+    /// every chunk it wrote, in order.
+    ///
+    pub async fn send_streaming(&self, input: I) -> Result<(MsgResult<O, E>, Vec<u8>), Error>
+    where
+        <N as NetLayer>::Error: std::fmt::Display,
+    {
+        let bytes = D::encode(Message::Stream(input)).map_err(Error::Serialize)?;
+        let reply = self.send_raw(&bytes, &[]).await?;
+
+        Ok((decode_reply::<O, E, D>(reply.body)?, reply.streamed))
+    }
+
+    ///
+    /// message the actor behind our address without waiting for its handler to run.
+    ///
+    /// returns [`Reply::Accepted`] as soon as the actor queued the task. its output is discarded.
+    ///
+    pub async fn tell(&self, input: I) -> Result<MsgResult<O, E>, Error>
+    where
+        <N as NetLayer>::Error: std::fmt::Display,
+    {
+        self.send(Message::Tell(input)).await
+    }
+
+    ///
+    /// ask the actor behind our address for its [`TypeSignature`], e.g. to check both ends agree on types
+    ///
+    /// a request that reached the actor but failed there (e.g. because it's busy) is reported as
+    /// [`Error::Recv`] carrying the actor's [`MsgError`](crate::messaging::MsgError), wrapped in
+    /// [`Error::WithContext`].
+    ///
+    pub async fn describe(&self) -> Result<TypeSignature, Error>
+    where
+        E: std::fmt::Display,
+        <N as NetLayer>::Error: std::fmt::Display,
+    {
+        match self.send(Message::Describe).await? {
+            Ok(Reply::Describe(signature)) => Ok(signature),
+            Ok(_) => Err(self.with_context(Error::Protocol(
+                "unexpected reply to describe request".into(),
+            ))),
+            Err(err) => {
+                Err(self.with_context(Error::Recv(format!("describe request failed: {err}"))))
+            }
+        }
+    }
+
+    ///
+    /// try to message the actor behind our address, aborting as soon as `token` is cancelled.
+    ///
+    /// on cancellation the underlying stream is shut down and [`Error::Cancelled`] is returned.
+    /// otherwise this fails like [`Self::send()`], but only re-dials when writing the request fails.
+    ///
+    pub async fn send_with_cancel(
+        &self,
+        msg: Message<I>,
+        token: CancellationToken,
+    ) -> Result<MsgResult<O, E>, Error>
+    where
+        <N as NetLayer>::Error: std::fmt::Display,
+    {
+        let bytes = D::encode(msg).map_err(Error::Serialize)?;
+
+        let (mut stream, _) = tokio::select! {
+            stream = self.dial(&bytes, &[]) => stream.map_err(|err| self.with_context(err))?,
+            _ = token.cancelled() => return Err(Error::Cancelled),
+        };
+
+        let reply = tokio::select! {
+            reply = self.read_reply(&mut stream) => Some(reply),
+            _ = token.cancelled() => None,
+        };
+
+        let Some(reply) = reply else {
+            tracing::debug!("remote handle: send cancelled, closing stream");
+            let _ = stream.shutdown().await;
+
+            return Err(Error::Cancelled);
+        };
+
+        let reply = reply.map_err(|err| self.with_context(err))?;
+
+        decode_reply::<O, E, D>(reply.body)
+    }
+
+    ///
+    /// like [`Self::send()`], but also returning a [`WireTrace`] of what went over the wire.
+    ///
+    /// raw request and response bytes are only captured with the `debug_wire` feature.
+    ///
+    pub async fn send_debug(&self, msg: Message<I>) -> Result<(MsgResult<O, E>, WireTrace), Error>
+    where
+        <N as NetLayer>::Error: std::fmt::Display,
+    {
+        let bytes = D::encode(msg).map_err(Error::Serialize)?;
+        let res = self.send_raw(&bytes, &[]).await?.body;
+
+        let trace = WireTrace {
+            id: self.addr().peer_id().bytes().to_vec(),
+            request_len: bytes.len(),
+            response_len: res.len(),
+            #[cfg(feature = "debug_wire")]
+            request: bytes,
+            #[cfg(feature = "debug_wire")]
+            response: res.clone(),
+        };
+
+        Ok((decode_reply::<O, E, D>(res)?, trace))
+    }
+
+    async fn connect(&self) -> Result<impl AsyncMsgStream, Error>
+    where
+        <N as NetLayer>::Error: std::fmt::Display,
+    {
+        self.netlayer
+            .connect(self.address.host())
+            .await
+            .map_err(|err| {
+                tracing::error!("remote handle: failed to connect - {err}");
+                Error::Connect(err.to_string())
+            })
+    }
+
+    async fn send_raw(&self, bytes: &[u8], key: &[u8]) -> Result<RawReply, Error>
+    where
+        <N as NetLayer>::Error: std::fmt::Display,
+    {
+        self.dial_and_exchange(bytes, key)
+            .await
+            .map_err(|err| self.with_context(err))
+    }
+
+    fn with_context(&self, source: Error) -> Error {
+        Error::WithContext {
+            addr: self.address.clone(),
+            source: Box::new(source),
+        }
+    }
+
+    async fn dial_and_exchange(&self, bytes: &[u8], key: &[u8]) -> Result<RawReply, Error>
+    where
+        <N as NetLayer>::Error: std::fmt::Display,
+    {
+        let (mut stream, redialed) = self.dial(bytes, key).await?;
+
+        match self.read_reply(&mut stream).await {
+            Err(Error::Disconnected) if self.auto_redial && !redialed => {
+                tracing::warn!("remote handle: peer hung up before replying, re-dialing");
+                let mut stream = self.connect().await?;
+                self.write_request(&mut stream, bytes, key).await?;
+
+                self.read_reply(&mut stream).await
+            }
+            res => res,
+        }
+    }
+
+    ///
+    /// connect and write a request, re-dialing once if writing fails and `auto_redial` is on.
+    /// also tells whether it re-dialed.
+    ///
+    async fn dial(&self, bytes: &[u8], key: &[u8]) -> Result<(impl AsyncMsgStream, bool), Error>
+    where
+        <N as NetLayer>::Error: std::fmt::Display,
+    {
+        let mut stream = self.connect().await?;
+
+        match self.write_request(&mut stream, bytes, key).await {
+            Err(Error::Send(err)) if self.auto_redial => {
+                tracing::warn!("remote handle: write failed, re-dialing - {err}");
+                stream = self.connect().await?;
+                self.write_request(&mut stream, bytes, key).await?;
+
+                Ok((stream, true))
+            }
+            res => res.map(|_| (stream, false)),
+        }
+    }
+
+    async fn write_request<S>(&self, stream: &mut S, bytes: &[u8], key: &[u8]) -> Result<(), Error>
+    where
+        S: AsyncMsgStream,
+    {
+        let id = self.addr().peer_id();
+        let id_len = self.addr().peer_id().len() as u16;
+
+        stream.write_u16(PROTOCOL_VERSION).await.map_err(|err| {
+            tracing::error!("remote handle: failed to send protocol version - {err}");
+            Error::Send(err.to_string())
+        })?;
+
+        stream.write_u16(id_len).await.map_err(|err| {
+            tracing::error!("remote handle: failed to send peer ID size - {err}");
+            Error::Send(err.to_string())
+        })?;
+
+        stream.write_all(id.bytes()).await.map_err(|err| {
+            tracing::error!("remote handle: failed to send peer ID - {err}");
+            Error::Send(err.to_string())
+        })?;
+
+        stream.write_u32(bytes.len() as u32).await.map_err(|err| {
+            tracing::error!("remote handle: failed to send message size - {err}");
+            Error::Send(err.to_string())
+        })?;
+
+        stream.write_all(bytes).await.map_err(|err| {
+            tracing::error!("remote handle: failed to send message - {err}");
+            Error::Send(err.to_string())
+        })?;
+
+        stream.write_u16(key.len() as u16).await.map_err(|err| {
+            tracing::error!("remote handle: failed to send idempotency key size - {err}");
+            Error::Send(err.to_string())
+        })?;
+
+        stream.write_all(key).await.map_err(|err| {
+            tracing::error!("remote handle: failed to send idempotency key - {err}");
+            Error::Send(err.to_string())
+        })?;
+
+        stream.flush().await.map_err(|err| {
+            tracing::error!("remote handle: failed to flush message - {err}");
+            Error::Send(err.to_string())
+        })?;
+
+        Ok(())
+    }
+
+    async fn read_reply<S>(&self, stream: &mut S) -> Result<RawReply, Error>
+    where
+        S: AsyncMsgStream,
+    {
+        let mut streamed = vec![];
+
+        let status = loop {
+            let status = match stream.read_u8().await {
+                Ok(status) => status,
+                Err(err) if streamed.is_empty() && hung_up(&err) => {
+                    tracing::error!("remote handle: peer hung up before replying - {err}");
+                    return Err(Error::Disconnected);
+                }
+                Err(err) => {
+                    tracing::error!("remote handle: failed to receive reply status - {err}");
+                    return Err(Error::Recv(err.to_string()));
+                }
+            };
+
+            if status != STATUS_CHUNK {
+                break status;
+            }
+
+            let size = stream.read_u32().await.map_err(|err| {
+                tracing::error!("remote handle: failed to receive chunk size - {err}");
+                Error::Recv(err.to_string())
+            })?;
+
+            let chunk = read_chunked(stream, size as usize, DEFAULT_READ_CHUNK_SIZE)
+                .await
+                .map_err(|err| {
+                    tracing::error!("remote handle: failed to receive chunk - {err}");
+                    Error::Recv(err.to_string())
+                })?;
+
+            streamed.extend_from_slice(&chunk);
+        };
+
+        if status == STATUS_VERSION_REJECTED {
+            let min_version = stream.read_u16().await.map_err(|err| {
+                tracing::error!("remote handle: failed to receive minimum version - {err}");
+                Error::Recv(err.to_string())
+            })?;
+
+            tracing::error!("remote handle: router requires protocol version {min_version}");
+            return Err(Error::VersionRejected(min_version));
+        }
+
+        if status != STATUS_OK {
+            tracing::error!("remote handle: unknown reply status {status}");
+            return Err(Error::Recv(format!("unknown reply status {status}")));
+        }
+
+        let size = stream.read_u32().await.map_err(|err| {
+            tracing::error!("remote handle: failed to receive message size - {err}");
+            Error::Recv(err.to_string())
+        })?;
+
+        let res_buffer = read_chunked(stream, size as usize, DEFAULT_READ_CHUNK_SIZE)
+            .await
+            .map_err(|err| {
+                tracing::error!("remote handle: failed to receive message - {err}");
+                Error::Recv(err.to_string())
             })?;
 
-        let id = self.addr().peer_id();
-        let id_len = self.addr().peer_id().len() as u16;
+        let request_id = stream.read_u64().await.map_err(|err| {
+            tracing::error!("remote handle: failed to receive request ID - {err}");
+            Error::Recv(err.to_string())
+        })?;
+
+        Ok(RawReply {
+            body: res_buffer,
+            streamed,
+            request_id,
+        })
+    }
+
+    ///
+    /// point this handle to another address, e.g. after the actor migrated or was republished
+    /// somewhere else. the net layer is kept.
+    ///
+    /// fails if the address is meant for a different net layer or its host isn't valid for this
+    /// one, leaving the handle unchanged.
+    ///
+    pub fn update_address(&mut self, address: &ActorAddress) -> Result<(), Error> {
+        address.check_proto::<N>().map_err(Error::Address)?;
+        address.check_host::<N>().map_err(Error::Address)?;
+        self.address = address.to_owned();
+
+        Ok(())
+    }
+
+    ///
+    /// whether our net layer can currently carry traffic, without waiting. see [`NetLayer::is_ready()`].
+    ///
+    pub fn is_ready(&self) -> bool {
+        self.netlayer.is_ready()
+    }
+
+    /// [`ActorAddress`] pointed to by this handle
+    pub fn addr(&self) -> &ActorAddress {
+        &self.address
+    }
+}
+
+///
+/// send already encoded `bytes` to `address` through `netlayer`, returning the encoded reply.
+///
+#[cfg(any(feature = "tcp", feature = "tor"))]
+pub(crate) async fn send_bytes<N>(
+    address: &ActorAddress,
+    netlayer: N,
+    bytes: &[u8],
+) -> Result<Vec<u8>, Error>
+where
+    N: NetLayer,
+    <N as NetLayer>::Error: Display,
+{
+    // types only matter for encoding and decoding, which is up to the caller here
+    let handle =
+        RemoteHandle::<(), (), (), dencoder::bitcode::BitcodeDencoder, N>::new(address, netlayer);
+
+    handle.send_raw(bytes, &[]).await.map(|reply| reply.body)
+}
+
+///
+/// decode a reply, naming the output type it was expected to carry if it doesn't decode.
+///
+/// dencoders can't tell a reply for a different type from a corrupted one, so both end up here.
+///
+fn decode_reply<O, E, D>(body: Vec<u8>) -> Result<MsgResult<O, E>, Error>
+where
+    O: DeserializeOwned,
+    E: DeserializeOwned,
+    D: Dencoder,
+{
+    D::decode(body).map_err(|source| Error::ReplyType {
+        expected: std::any::type_name::<O>(),
+        source,
+    })
+}
+
+/// reply as read off the wire, before decoding
+struct RawReply {
+    body: Vec<u8>,
+    streamed: Vec<u8>,
+    request_id: u64,
+}
+
+///
+/// record of a single request/reply exchange, as seen on the wire
+///
+#[derive(Debug, Clone)]
+pub struct WireTrace {
+    id: Vec<u8>,
+    request_len: usize,
+    response_len: usize,
+    #[cfg(feature = "debug_wire")]
+    request: Vec<u8>,
+    #[cfg(feature = "debug_wire")]
+    response: Vec<u8>,
+}
+
+impl WireTrace {
+    /// peer ID bytes sent in the request
+    pub fn id(&self) -> &[u8] {
+        &self.id
+    }
+
+    /// length of the encoded request body
+    pub fn request_len(&self) -> usize {
+        self.request_len
+    }
+
+    /// length of the encoded reply body
+    pub fn response_len(&self) -> usize {
+        self.response_len
+    }
+
+    /// raw encoded request body
+    #[cfg(feature = "debug_wire")]
+    pub fn request(&self) -> &[u8] {
+        &self.request
+    }
+
+    /// raw encoded reply body
+    #[cfg(feature = "debug_wire")]
+    pub fn response(&self) -> &[u8] {
+        &self.response
+    }
+}
+
+#[derive(Debug)]
+enum RouterMessage {
+    Stop,
+    Attach { peer: Peer, peer_id: Option<PeerId> },
+    Revoke(ActorAddress),
+    UpdateOpts(RouterOpts),
+    Metrics,
+    ExportState,
+    Peers,
+}
+
+#[derive(Debug)]
+enum RouterReply {
+    Accepted,
+    Address(ActorAddress),
+    Metrics(RouterMetrics),
+    State(RouterState),
+    Peers(Vec<(PeerId, UntypedHandle)>),
+}
+
+///
+/// actor attached to a router, or the pending spawn of one attached lazily
+///
+#[derive(Clone)]
+enum Peer {
+    Ready(UntypedHandle),
+    // none once the factory panicked
+    Lazy(Shared<BoxFuture<'static, Option<UntypedHandle>>>),
+}
+
+impl Peer {
+    /// handle for this peer, spawning it first if it's the first time it's needed
+    async fn handle(self) -> Result<UntypedHandle, Error> {
+        match self {
+            Peer::Ready(handle) => Ok(handle),
+            Peer::Lazy(spawned) => spawned
+                .await
+                .ok_or_else(|| Error::Send("lazy actor failed to spawn".into())),
+        }
+    }
+
+    /// handle for this peer, if it's spawned already
+    fn spawned(&self) -> Option<UntypedHandle> {
+        match self {
+            Peer::Ready(handle) => Some(handle.clone()),
+            Peer::Lazy(spawned) => spawned.peek().cloned().flatten(),
+        }
+    }
+}
+
+impl std::fmt::Debug for Peer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Peer::Ready(handle) => f.debug_tuple("Ready").field(handle).finish(),
+            Peer::Lazy(spawned) => f
+                .debug_tuple("Lazy")
+                .field(&spawned.peek().is_some())
+                .finish(),
+        }
+    }
+}
+
+/// whether `err` means the peer closed the connection on us
+fn hung_up(err: &std::io::Error) -> bool {
+    matches!(
+        err.kind(),
+        std::io::ErrorKind::UnexpectedEof
+            | std::io::ErrorKind::ConnectionReset
+            | std::io::ErrorKind::ConnectionAborted
+    )
+}
+
+fn unexpected_reply(expected: &str, reply: RouterReply) -> Error {
+    tracing::error!("router: expected {expected} reply, got {reply:?}");
+
+    Error::Protocol(format!("expected {expected} reply, got {reply:?}"))
+}
+
+#[cfg(feature = "metrics")]
+fn drop_reason(err: &Error) -> &'static str {
+    match err {
+        Error::VersionRejected(_) => "version_rejected",
+        Error::Disconnected => "disconnected",
+        Error::Recv(_) => "recv",
+        Error::Send(_) => "send",
+        _ => "other",
+    }
+}
+
+///
+/// replies sent to requests carrying an idempotency key, by peer and key
+///
+#[derive(Debug, Default)]
+struct ReplyCache {
+    replies: HashMap<(PeerId, Vec<u8>), (Instant, CachedReply)>,
+    // insertion order, oldest first
+    order: VecDeque<(PeerId, Vec<u8>)>,
+    // last claim handed out, telling runs with the same key apart
+    claims: u64,
+}
+
+/// everything a request was answered with
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SentReply {
+    chunks: Vec<Vec<u8>>,
+    reply: Vec<u8>,
+}
+
+#[derive(Debug)]
+enum CachedReply {
+    /// the first request with this key is still running
+    Pending {
+        claim: u64,
+        reply: Shared<oneshot::Receiver<SentReply>>,
+    },
+    Done(SentReply),
+}
+
+/// what to do with a request carrying an idempotency key
+enum Claim {
+    Replay(SentReply),
+    Wait(Shared<oneshot::Receiver<SentReply>>),
+    Run {
+        claim: u64,
+        sender: oneshot::Sender<SentReply>,
+    },
+}
+
+impl ReplyCache {
+    fn get(&self, key: &(PeerId, Vec<u8>), ttl: Duration) -> Option<SentReply> {
+        match self.replies.get(key) {
+            Some((at, CachedReply::Done(sent))) if at.elapsed() < ttl => Some(sent.clone()),
+            _ => None,
+        }
+    }
+
+    ///
+    /// replay the reply cached for `key`, wait for the request running with it, or mark it
+    /// as running for the caller to [`Claimed::complete()`].
+    ///
+    fn claim(&mut self, key: &(PeerId, Vec<u8>), ttl: Duration, capacity: usize) -> Claim {
+        if let Some((_, CachedReply::Pending { reply, .. })) = self.replies.get(key) {
+            return Claim::Wait(reply.clone());
+        }
+
+        if let Some(sent) = self.get(key, ttl) {
+            return Claim::Replay(sent);
+        }
+
+        self.claims += 1;
+        let claim = self.claims;
+        let (sender, receiver) = oneshot::channel();
+        let pending = CachedReply::Pending {
+            claim,
+            reply: receiver.shared(),
+        };
+        self.insert(key.clone(), pending, ttl, capacity);
+
+        Claim::Run { claim, sender }
+    }
+
+    /// forget a run that ended without a reply, unless another one took its place
+    fn abandon(&mut self, key: &(PeerId, Vec<u8>), claim: u64) {
+        let ours = matches!(
+            self.replies.get(key),
+            Some((_, CachedReply::Pending { claim: pending, .. })) if *pending == claim
+        );
+
+        if ours {
+            self.replies.remove(key);
+            self.order.retain(|k| k != key);
+        }
+    }
+
+    fn insert(
+        &mut self,
+        key: (PeerId, Vec<u8>),
+        reply: CachedReply,
+        ttl: Duration,
+        capacity: usize,
+    ) {
+        if capacity == 0 {
+            return;
+        }
+
+        // drop expired replies, then the oldest ones until there's room. runs are never
+        // dropped, or a repeat of one would run again
+        let replies = &mut self.replies;
+        self.order.retain(|k| {
+            let expired = replies.get(k).is_none_or(|(at, reply)| {
+                matches!(reply, CachedReply::Done(_)) && at.elapsed() >= ttl
+            });
+
+            if expired {
+                replies.remove(k);
+            }
+
+            !expired
+        });
+
+        if !self.replies.contains_key(&key) {
+            while self.replies.len() >= capacity {
+                let oldest_done = self
+                    .order
+                    .iter()
+                    .position(|k| matches!(self.replies.get(k), Some((_, CachedReply::Done(_)))));
+
+                let Some(oldest_done) = oldest_done else {
+                    // only runs left, so this one goes uncached
+                    return;
+                };
+
+                if let Some(oldest) = self.order.remove(oldest_done) {
+                    self.replies.remove(&oldest);
+                }
+            }
+        }
+
+        if self
+            .replies
+            .insert(key.clone(), (Instant::now(), reply))
+            .is_none()
+        {
+            self.order.push_back(key);
+        }
+    }
+}
+
+///
+/// request running with an idempotency key, abandoned if dropped before completing
+///
+struct Claimed<'a> {
+    replies: &'a Mutex<ReplyCache>,
+    key: (PeerId, Vec<u8>),
+    claim: u64,
+    sender: Option<oneshot::Sender<SentReply>>,
+    chunks: Vec<Vec<u8>>,
+}
+
+impl Claimed<'_> {
+    /// cache the reply, handing it to repeats waiting for it
+    fn complete(mut self, reply: Vec<u8>, ttl: Duration, capacity: usize) {
+        let sent = SentReply {
+            chunks: std::mem::take(&mut self.chunks),
+            reply,
+        };
+
+        self.replies.lock().expect("poisoned lock").insert(
+            self.key.clone(),
+            CachedReply::Done(sent.clone()),
+            ttl,
+            capacity,
+        );
+
+        if let Some(sender) = self.sender.take() {
+            let _ = sender.send(sent);
+        }
+    }
+}
+
+impl Drop for Claimed<'_> {
+    fn drop(&mut self) {
+        if self.sender.is_some() {
+            self.replies
+                .lock()
+                .expect("poisoned lock")
+                .abandon(&self.key, self.claim);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    connections: AtomicU64,
+    relayed: AtomicU64,
+    dropped: AtomicU64,
+}
+
+///
+/// snapshot of a router's state and traffic
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouterMetrics {
+    attached: usize,
+    connections: u64,
+    relayed: u64,
+    dropped: u64,
+}
+
+impl RouterMetrics {
+    /// number of currently attached capabilities
+    pub fn attached(&self) -> usize {
+        self.attached
+    }
+
+    /// number of connections accepted so far
+    pub fn connections(&self) -> u64 {
+        self.connections
+    }
+
+    /// number of messages successfully relayed to an actor and replied to
+    pub fn relayed(&self) -> u64 {
+        self.relayed
+    }
+
+    /// number of connections dropped without a reply (unknown peer, timeout, bad frame...)
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+///
+/// attached peers of a router, as exported by [`RouterHandle::export_state()`]
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouterState {
+    peers: Vec<ActorAddress>,
+}
+
+impl RouterState {
+    /// addresses of the attached peers when exported, ordered by peer ID
+    pub fn peers(&self) -> &[ActorAddress] {
+        &self.peers
+    }
+}
+
+///
+/// raw reply from one of the actors reached by [`RouterHandle::broadcast()`]
+///
+#[derive(Debug)]
+pub struct ReplyEnvelope {
+    peer_id: PeerId,
+    reply: Result<Vec<u8>, remote::Error>,
+}
+
+impl ReplyEnvelope {
+    /// peer this reply came from
+    pub fn peer_id(&self) -> &PeerId {
+        &self.peer_id
+    }
+
+    /// encoded reply, or why the message couldn't be relayed to this peer
+    pub fn raw(&self) -> Result<&[u8], &remote::Error> {
+        self.reply.as_deref()
+    }
+
+    ///
+    /// decode this reply as coming from an actor with output `O` and error `E`.
+    ///
+    /// can be attempted with different types, e.g. until one matches the peer's actor.
+    ///
+    pub fn decode_as<O, E, D>(&self) -> Result<MsgResult<O, E>, Error>
+    where
+        O: DeserializeOwned,
+        E: DeserializeOwned,
+        D: Dencoder,
+    {
+        let raw = self.raw().map_err(|err| Error::Send(err.to_string()))?;
+
+        decode_reply::<O, E, D>(raw.to_vec())
+    }
+}
+
+///
+/// errors when creating a routing, or messaging an actor with it
+///
+#[allow(missing_docs)]
+#[derive(Debug)]
+pub enum Error {
+    Init(String),
+    Connect(String),
+    Serialize(dencoder::Error),
+    Send(String),
+    Recv(String),
+    Address(address::Error),
+    Cancelled,
+    VersionRejected(u16),
+    InvalidOpts(String),
+    CapacityReached,
+    Protocol(String),
+    Disconnected,
+    MissingHandles(Vec<PeerId>),
+    DuplicatePeers(Vec<PeerId>),
+    RouterStopped,
+    ReplyType {
+        expected: &'static str,
+        source: dencoder::Error,
+    },
+    WithContext {
+        addr: ActorAddress,
+        source: Box<Error>,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Init(ctx) => write!(f, "failed to init router: {ctx}"),
+            Error::Connect(ctx) => write!(f, "failed to connect to endpoint: {ctx}"),
+            Error::Serialize(ctx) => write!(f, "failed to encode/decode message: {ctx}"),
+            Error::Send(ctx) => write!(f, "failed to send message: {ctx}"),
+            Error::Recv(ctx) => write!(f, "failed to receive message: {ctx}"),
+            Error::Address(ctx) => write!(f, "failed to create address: {ctx}"),
+            Error::Cancelled => write!(f, "operation cancelled"),
+            Error::InvalidOpts(ctx) => write!(f, "invalid router options: {ctx}"),
+            Error::CapacityReached => write!(f, "router can't attach any more actors"),
+            Error::Protocol(ctx) => write!(f, "unexpected reply from router: {ctx}"),
+            Error::Disconnected => write!(f, "peer disconnected"),
+            Error::RouterStopped => write!(f, "router already stopped"),
+            Error::ReplyType { expected, source } => {
+                write!(f, "reply doesn't decode as a {expected} reply: {source}")
+            }
+            Error::MissingHandles(ids) => {
+                let ids: Vec<String> = ids.iter().map(|id| id.redacted().to_string()).collect();
+                write!(f, "no handle to restore peers {}", ids.join(", "))
+            }
+            Error::DuplicatePeers(ids) => {
+                let ids: Vec<String> = ids.iter().map(|id| id.redacted().to_string()).collect();
+                write!(f, "peers listed more than once: {}", ids.join(", "))
+            }
+            Error::VersionRejected(min) => {
+                write!(
+                    f,
+                    "protocol version rejected, router requires {min} or newer"
+                )
+            }
+            Error::WithContext { addr, source } => write!(
+                f,
+                "{source} (remote: {}:{}@{})",
+                addr.proto_id(),
+                addr.peer_id().redacted(),
+                addr.host()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::WithContext { source, .. } => Some(source.as_ref()),
+            Error::ReplyType { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{
+            Arc,
+            atomic::{AtomicBool, AtomicU64, Ordering},
+        },
+        time::{Duration, Instant},
+    };
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt, BufWriter},
+        net::TcpStream,
+        sync::{mpsc, oneshot},
+    };
+    use tokio_util::either::Either;
+    use tokio_util::sync::CancellationToken;
+
+    use crate::{
+        actors::{
+            Actor, Context,
+            remote::{
+                self,
+                address::{self, ActorAddress, PeerId},
+                dencoder::{Dencoder, bitcode::BitcodeDencoder},
+                netlayer::{
+                    AsyncMsgStream, NetLayer,
+                    tcp_layer::{TcpError, TcpNetLayer},
+                },
+                router::{
+                    CachedReply, Claim, Error, MAX_MSG_SIZE_LIMIT, PROTOCOL_VERSION, RemoteHandle,
+                    ReplyCache, Router, RouterHandle, RouterMessage, RouterOpts, RouterReply,
+                    RouterState, STATUS_VERSION_REJECTED, SentReply,
+                },
+            },
+            tests::{Chunker, Echo, Mult, Sleeper, SomeError},
+        },
+        messaging::{Message, MsgError, Reply},
+    };
+
+    #[tokio::test]
+    async fn spawn_and_message() {
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+        assert!(addr.host().starts_with("127.0.0.1"));
+
+        let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        let res = remote.send(Message::Task(5)).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Task(15))));
+    }
+
+    #[tokio::test]
+    async fn spawn_with_id() {
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let peer_id = PeerId::new().unwrap();
+        let addr = router
+            .attach_with_id(handle, peer_id.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(peer_id, *addr.peer_id());
+
+        let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        let res = remote.send(Message::Task(5)).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Task(15))));
+    }
+
+    #[tokio::test]
+    async fn ping() {
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+
+        let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        let res = remote.send(Message::Ping).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Accepted)));
+    }
+
+    #[tokio::test]
+    async fn stop() {
+        let (_, mut handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+
+        handle.allow_stop(true);
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+
+        let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        let res = remote.send(Message::Stop).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Accepted)));
+
+        remote.send(Message::Ping).await.unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn revoke() {
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+
+        let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        let res = remote.send(Message::Ping).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Accepted)));
+
+        router.revoke(&addr).await.unwrap();
+
+        remote.send(Message::Ping).await.unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn send_with_cancel() {
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Sleeper)
+            .await
+            .unwrap();
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+
+        let remote = RemoteHandle::<u64, u64, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            canceller.cancel();
+        });
+
+        let res = tokio::time::timeout(
+            Duration::from_millis(1000),
+            remote.send_with_cancel(Message::Task(3000), token),
+        )
+        .await
+        .expect("cancellation should return promptly");
+
+        assert!(matches!(res, Err(Error::Cancelled)));
+
+        // failures carry the address like any other send
+        router.stop().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let res = remote
+            .send_with_cancel(Message::Task(0), CancellationToken::new())
+            .await;
+        assert!(matches!(
+            res,
+            Err(Error::WithContext { addr: ctx, source })
+                if ctx.peer_id() == addr.peer_id() && matches!(*source, Error::Connect(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn update_opts() {
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::new(5000, 1)))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+
+        let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        remote.send(Message::Task(5)).await.unwrap_err();
+
+        router.update_opts(RouterOpts::default()).await.unwrap();
+
+        let res = remote.send(Message::Task(5)).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Task(15))));
+
+        let opts = RouterOpts::builder().channel_capacity(16).build().unwrap();
+        let res = router.update_opts(opts).await;
+        assert!(matches!(res, Err(Error::InvalidOpts(_))));
+
+        let opts = RouterOpts::builder().await_ready(false).build().unwrap();
+        let res = router.update_opts(opts).await;
+        assert!(matches!(res, Err(Error::InvalidOpts(_))));
+
+        // rejected updates leave the previous options in place
+        let res = remote.send(Message::Task(5)).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Task(15))));
+    }
+
+    struct MultSpawner {
+        router: RouterHandle,
+    }
+
+    impl Actor<u32, ActorAddress, SomeError> for MultSpawner {
+        async fn handler(&self, input: u32, _ctx: &Context) -> Result<ActorAddress, SomeError> {
+            let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: input })
+                .await
+                .map_err(|_| SomeError)?;
+
+            self.router.attach(handle).await.map_err(|_| SomeError)
+        }
+    }
+
+    #[tokio::test]
+    async fn capability_in_output() {
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(MultSpawner {
+            router: router.clone(),
+        })
+        .await
+        .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+
+        let spawner =
+            RemoteHandle::<u32, ActorAddress, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+                &addr,
+                TcpNetLayer::new(),
+            );
+
+        let sub_addr = match spawner.send(Message::Task(4)).await.unwrap() {
+            Ok(Reply::Task(addr)) => addr,
+            _ => panic!("expected a sub-actor address"),
+        };
+
+        assert_ne!(addr.peer_id(), sub_addr.peer_id());
+
+        let sub = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &sub_addr,
+            TcpNetLayer::new(),
+        );
+
+        let res = sub.send(Message::Task(5)).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Task(20))));
+    }
+
+    #[tokio::test]
+    async fn send_debug() {
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+
+        let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        let (res, trace) = remote.send_debug(Message::Task(5)).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Task(15))));
+
+        let encoded = BitcodeDencoder::encode(Message::Task(5u32)).unwrap();
+        assert_eq!(encoded.len(), trace.request_len());
+        assert_eq!(addr.peer_id().bytes(), trace.id());
+        assert!(trace.response_len() > 0);
+
+        #[cfg(feature = "debug_wire")]
+        assert_eq!(encoded, trace.request());
+    }
+
+    /// TCP layer whose accepted streams only hit the socket when flushed
+    struct BufferedLayer(TcpNetLayer);
+
+    impl NetLayer for BufferedLayer {
+        type Error = TcpError;
+
+        fn name() -> &'static str {
+            "tcp"
+        }
+
+        async fn connect(&self, addr: &str) -> Result<impl AsyncMsgStream, Self::Error> {
+            self.0.connect(addr).await
+        }
+
+        async fn init(&mut self) -> Result<(), Self::Error> {
+            self.0.init().await
+        }
+
+        async fn accept(&self) -> Result<impl AsyncMsgStream, Self::Error> {
+            Ok(BufWriter::with_capacity(64 * 1024, self.0.accept().await?))
+        }
+
+        async fn address(&self) -> Result<String, Self::Error> {
+            self.0.address().await
+        }
+    }
+
+    #[tokio::test]
+    async fn buffered_reply_is_flushed() {
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+
+        let router = Router::with_netlayer(
+            BufferedLayer(TcpNetLayer::new()),
+            Some(RouterOpts::default()),
+        )
+        .await
+        .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+
+        let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        let res = tokio::time::timeout(Duration::from_millis(1000), remote.send(Message::Task(5)))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(matches!(res, Ok(Reply::Task(15))));
+    }
+
+    #[tokio::test]
+    async fn chunked_read() {
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Echo)
+            .await
+            .unwrap();
+
+        let opts = RouterOpts {
+            read_chunk_size: 1000,
+            ..Default::default()
+        };
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(opts))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+
+        let remote = RemoteHandle::<Vec<u8>, Vec<u8>, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        let body: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+
+        match remote.send(Message::Task(body.clone())).await.unwrap() {
+            Ok(Reply::Task(echoed)) => assert_eq!(body, echoed),
+            _ => panic!("expected echoed body"),
+        }
+    }
+
+    /// TCP layer whose listener is gone for good
+    struct DeadLayer(TcpNetLayer);
+
+    impl NetLayer for DeadLayer {
+        type Error = TcpError;
+
+        fn name() -> &'static str {
+            "tcp"
+        }
+
+        async fn connect(&self, addr: &str) -> Result<impl AsyncMsgStream, Self::Error> {
+            self.0.connect(addr).await
+        }
+
+        async fn init(&mut self) -> Result<(), Self::Error> {
+            self.0.init().await
+        }
+
+        async fn accept(&self) -> Result<impl AsyncMsgStream, Self::Error> {
+            Err::<TcpStream, _>(TcpError::Bind("listener lost".into()))
+        }
+
+        async fn address(&self) -> Result<String, Self::Error> {
+            self.0.address().await
+        }
+    }
+
+    #[tokio::test]
+    async fn attach_after_stop() {
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle.clone()).await.unwrap();
+        router.stop().await.unwrap();
+
+        assert!(matches!(
+            router.attach(handle).await,
+            Err(Error::RouterStopped)
+        ));
+        assert!(matches!(
+            router.revoke(&addr).await,
+            Err(Error::RouterStopped)
+        ));
+        assert!(matches!(
+            router.update_opts(RouterOpts::default()).await,
+            Err(Error::RouterStopped)
+        ));
+    }
+
+    #[tokio::test]
+    async fn fatal_accept_error_stops_router() {
+        let router = Router::with_netlayer(DeadLayer(TcpNetLayer::new()), None)
+            .await
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        router.metrics().await.unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn advertised_host() {
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+
+        let opts = RouterOpts {
+            advertised_host: Some("example.com:9000".into()),
+            ..Default::default()
+        };
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(opts))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+
+        assert_eq!("example.com:9000", addr.host());
+        assert_ne!(router.host_address(), addr.host());
+    }
+
+    #[tokio::test]
+    async fn public_address() {
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+
+        let layer = TcpNetLayer::new().with_public_address("example.com:9000");
+        let router = Router::with_netlayer(layer, Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+
+        assert_eq!("example.com:9000", addr.host());
+        assert!(router.host_address().starts_with("127.0.0.1"));
+    }
+
+    /// TCP layer that can't be cloned, like most real ones
+    struct UniqueLayer(TcpNetLayer);
+
+    impl NetLayer for UniqueLayer {
+        type Error = TcpError;
+
+        fn name() -> &'static str {
+            "tcp"
+        }
+
+        async fn connect(&self, addr: &str) -> Result<impl AsyncMsgStream, Self::Error> {
+            self.0.connect(addr).await
+        }
+
+        async fn init(&mut self) -> Result<(), Self::Error> {
+            self.0.init().await
+        }
+
+        async fn accept(&self) -> Result<impl AsyncMsgStream, Self::Error> {
+            self.0.accept().await
+        }
+
+        async fn address(&self) -> Result<String, Self::Error> {
+            self.0.address().await
+        }
+    }
+
+    #[tokio::test]
+    async fn cloned_handles_share_layer() {
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+
+        let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, UniqueLayer>::new(
+            &addr,
+            UniqueLayer(TcpNetLayer::new()),
+        );
+        let cloned = remote.clone();
+
+        let (a, b) = tokio::join!(
+            remote.send(Message::Task(5)),
+            tokio::spawn(async move { cloned.send(Message::Task(7)).await })
+        );
+
+        assert!(matches!(a.unwrap(), Ok(Reply::Task(15))));
+        assert!(matches!(b.unwrap().unwrap(), Ok(Reply::Task(21))));
+    }
+
+    #[tokio::test]
+    async fn from_str_with_layer() {
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap().to_string();
+
+        let remote =
+            RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::from_str_with_layer(
+                &addr,
+                TcpNetLayer::new(),
+            )
+            .unwrap();
+
+        let res = remote.send(Message::Task(5)).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Task(15))));
+
+        let res =
+            RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::from_str_with_layer(
+                "tcp:mfrggzdfmztwq2lk@127.0.0.1:9000",
+                TcpNetLayer::new(),
+            );
+        assert!(res.is_ok());
+
+        let res =
+            RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::from_str_with_layer(
+                "tcp@127.0.0.1:9000",
+                TcpNetLayer::new(),
+            );
+        assert!(matches!(
+            res,
+            Err(Error::Address(address::Error::Malformed))
+        ));
+
+        let res =
+            RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::from_str_with_layer(
+                "tor:mfrggzdfmztwq2lk@someaddress.onion:1234",
+                TcpNetLayer::new(),
+            );
+        assert!(matches!(res, Err(Error::Address(address::Error::Proto(_)))));
+
+        let res =
+            RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::from_str_with_layer(
+                "tcp:mfrggzdfmztwq2lk@someaddress.onion:1234",
+                TcpNetLayer::new(),
+            );
+        assert!(matches!(res, Err(Error::Address(address::Error::Host(_)))));
+    }
+
+    #[test]
+    fn try_new() {
+        let addr = ActorAddress::try_parse("tcp:mfrggzdfmztwq2lk@127.0.0.1:9000").unwrap();
+        let res = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::try_new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+        assert!(res.is_ok());
+
+        let addr = ActorAddress::try_parse("tcp:mfrggzdfmztwq2lk@someaddress.onion:1234").unwrap();
+        let res = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::try_new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+        assert!(matches!(res, Err(Error::Address(address::Error::Host(_)))));
+
+        let addr = ActorAddress::try_parse("tor:mfrggzdfmztwq2lk@someaddress.onion:1234").unwrap();
+        let res = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::try_new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+        assert!(matches!(res, Err(Error::Address(address::Error::Proto(_)))));
+    }
+
+    /// TCP layer whose first dialed stream is already dead
+    struct StaleLayer {
+        inner: TcpNetLayer,
+        stale: AtomicBool,
+    }
+
+    impl NetLayer for StaleLayer {
+        type Error = TcpError;
+
+        fn name() -> &'static str {
+            "tcp"
+        }
+
+        async fn connect(&self, addr: &str) -> Result<impl AsyncMsgStream, Self::Error> {
+            if self.stale.swap(false, Ordering::SeqCst) {
+                let (stream, _) = tokio::io::duplex(64);
+                return Ok(Either::Left(stream));
+            }
+
+            Ok(Either::Right(self.inner.connect(addr).await?))
+        }
+
+        async fn init(&mut self) -> Result<(), Self::Error> {
+            self.inner.init().await
+        }
+
+        async fn accept(&self) -> Result<impl AsyncMsgStream, Self::Error> {
+            self.inner.accept().await
+        }
+
+        async fn address(&self) -> Result<String, Self::Error> {
+            self.inner.address().await
+        }
+    }
+
+    #[tokio::test]
+    async fn auto_redial() {
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+
+        let stale_layer = || StaleLayer {
+            inner: TcpNetLayer::new(),
+            stale: AtomicBool::new(true),
+        };
+
+        let remote =
+            RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, _>::new(&addr, stale_layer());
+        let res = remote.send(Message::Task(5)).await;
+        assert!(matches!(
+            res,
+            Err(Error::WithContext { source, .. }) if matches!(*source, Error::Send(_))
+        ));
+
+        let remote =
+            RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, _>::new(&addr, stale_layer())
+                .with_auto_redial(true);
+        let res = remote.send(Message::Task(5)).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Task(15))));
+    }
+
+    #[tokio::test]
+    async fn describe() {
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+
+        let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        let signature = remote.describe().await.unwrap();
+        assert!(signature.input().contains("u32"));
+        assert!(signature.output().contains("u32"));
+    }
+
+    #[tokio::test]
+    async fn proto_name() {
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        assert_eq!(router.proto_name(), "tcp");
+
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+        let addr = router.attach(handle).await.unwrap();
+
+        assert_eq!(addr.proto_id(), router.proto_name());
+    }
+
+    #[tokio::test]
+    async fn error_context() {
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let addr = ActorAddress::new::<TcpNetLayer>(router.host_address()).unwrap();
+        router.stop().await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        let err = remote.send(Message::Task(5)).await.unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains(addr.host()));
+        assert!(msg.contains(&addr.peer_id().redacted().to_string()));
+        assert!(!msg.contains(&addr.peer_id().to_string()));
+
+        match err {
+            Error::WithContext { addr: ctx, source } => {
+                assert_eq!(ctx.peer_id(), addr.peer_id());
+                assert!(matches!(*source, Error::Connect(_)));
+            }
+            _ => panic!("expected WithContext error"),
+        }
+    }
+
+    #[tokio::test]
+    async fn min_client_version() {
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+
+        let opts = RouterOpts {
+            min_client_version: PROTOCOL_VERSION,
+            ..Default::default()
+        };
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(opts))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+
+        // hand-rolled client speaking an older version
+        let mut stream = TcpStream::connect(addr.host()).await.unwrap();
+        let msg = BitcodeDencoder::encode(Message::Task(5u32)).unwrap();
+
+        stream.write_u16(PROTOCOL_VERSION - 1).await.unwrap();
+        stream.write_u16(addr.peer_id().len() as u16).await.unwrap();
+        stream.write_all(addr.peer_id().bytes()).await.unwrap();
+        stream.write_u32(msg.len() as u32).await.unwrap();
+        stream.write_all(&msg).await.unwrap();
+
+        assert_eq!(stream.read_u8().await.unwrap(), STATUS_VERSION_REJECTED);
+        assert_eq!(stream.read_u16().await.unwrap(), PROTOCOL_VERSION);
+
+        let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        let res = remote.send(Message::Task(5)).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Task(15))));
+    }
+
+    #[tokio::test]
+    async fn unversioned_client() {
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+
+        // hand-rolled client from before versioning, starting with N_id
+        let mut stream = TcpStream::connect(addr.host()).await.unwrap();
+        let msg = BitcodeDencoder::encode(Message::Task(5u32)).unwrap();
+
+        stream.write_u16(addr.peer_id().len() as u16).await.unwrap();
+        stream.write_all(addr.peer_id().bytes()).await.unwrap();
+        stream.write_u32(msg.len() as u32).await.unwrap();
+        stream.write_all(&msg).await.unwrap();
+
+        assert_eq!(stream.read_u8().await.unwrap(), STATUS_VERSION_REJECTED);
+        assert_eq!(stream.read_u16().await.unwrap(), 1);
+
+        // version 0 isn't a known version either
+        let mut stream = TcpStream::connect(addr.host()).await.unwrap();
+        stream.write_u16(0).await.unwrap();
+
+        assert_eq!(stream.read_u8().await.unwrap(), STATUS_VERSION_REJECTED);
+    }
+
+    /// log sink shared with a test's tracing subscriber
+    #[derive(Clone, Default)]
+    struct LogCapture(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for LogCapture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn client_disconnect() {
+        let logs = LogCapture::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        // current-thread runtime, so the router's tasks log here too
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+
+        // hand-rolled client hanging up right after its ID
+        let mut stream = TcpStream::connect(addr.host()).await.unwrap();
+        stream.write_u16(PROTOCOL_VERSION).await.unwrap();
+        stream.write_u16(addr.peer_id().len() as u16).await.unwrap();
+        stream.write_all(addr.peer_id().bytes()).await.unwrap();
+        drop(stream);
+
+        while router.metrics().await.unwrap().dropped() == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("client disconnected"));
+        assert!(!logs.contains("ERROR"));
+    }
+
+    /// does nothing, with nothing
+    struct Nop;
+
+    impl Actor<(), (), SomeError> for Nop {
+        async fn handler(&self, _input: (), _ctx: &Context) -> Result<(), SomeError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn minimal_payload() {
+        let logs = LogCapture::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        // current-thread runtime, so the router's tasks log here too
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Nop)
+            .await
+            .unwrap();
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+
+        let remote = RemoteHandle::<(), (), SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        let res = remote.send(Message::Task(())).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Task(()))));
+
+        // hand-rolled client sending an empty body, which is still a message rather than a hang-up
+        let mut stream = TcpStream::connect(addr.host()).await.unwrap();
+        stream.write_u16(PROTOCOL_VERSION).await.unwrap();
+        stream.write_u16(addr.peer_id().len() as u16).await.unwrap();
+        stream.write_all(addr.peer_id().bytes()).await.unwrap();
+        stream.write_u32(0).await.unwrap();
+        stream.write_u16(0).await.unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while router.metrics().await.unwrap().dropped() == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("empty body was never counted as dropped");
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("failed to decode incoming message"));
+        assert!(!logs.contains("client disconnected"));
+
+        let res = remote.send(Message::Task(())).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Task(()))));
+    }
+
+    /// sleeps for `input` milliseconds, then bumps its counter
+    struct SlowCounter {
+        count: Arc<AtomicU64>,
+    }
+
+    impl Actor<u64, u64, SomeError> for SlowCounter {
+        async fn handler(&self, input: u64, _ctx: &Context) -> Result<u64, SomeError> {
+            tokio::time::sleep(Duration::from_millis(input)).await;
+
+            Ok(self.count.fetch_add(1, Ordering::SeqCst) + 1)
+        }
+    }
+
+    #[tokio::test]
+    async fn streaming() {
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Chunker)
+            .await
+            .unwrap();
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+
+        let remote = RemoteHandle::<u8, (), SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        let (res, body) = remote.send_streaming(4).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Accepted)));
+        assert_eq!(body, [[0; 4], [1; 4], [2; 4], [3; 4]].concat());
+
+        let (res, body) = remote.send_streaming(0).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Accepted)));
+        assert!(body.is_empty());
+
+        let res = remote.send(Message::Task(4)).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Task(()))));
+    }
+
+    #[tokio::test]
+    async fn export_and_restore() {
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let (_, double) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 2 })
+            .await
+            .unwrap();
+        let (_, triple) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+
+        let double_addr = router.attach(double.clone()).await.unwrap();
+        let triple_addr = router.attach(triple.clone()).await.unwrap();
+
+        let state = router.export_state().await.unwrap();
+        assert_eq!(state.peers().len(), 2);
+
+        // metadata only, so it can be persisted
+        let state = BitcodeDencoder::encode(state).unwrap();
+        let state: RouterState = BitcodeDencoder::decode(state).unwrap();
+
+        router.stop().await.unwrap();
+
+        let handles = HashMap::from([(double_addr.peer_id().clone(), double.clone())]);
+        let res = Router::restore(
+            TcpNetLayer::new(),
+            Some(RouterOpts::default()),
+            state.clone(),
+            handles,
+        )
+        .await;
+        let err = res.unwrap_err();
+        assert!(!err.to_string().contains(&triple_addr.peer_id().to_string()));
+        assert!(
+            matches!(err, Error::MissingHandles(ids) if ids == [triple_addr.peer_id().clone()])
+        );
+
+        let handles = HashMap::from([
+            (double_addr.peer_id().clone(), double),
+            (triple_addr.peer_id().clone(), triple),
+        ]);
+        let restored = Router::restore(
+            TcpNetLayer::new(),
+            Some(RouterOpts::default()),
+            state.clone(),
+            handles,
+        )
+        .await
+        .unwrap();
+
+        let restored_state = restored.export_state().await.unwrap();
+        let ids = |state: &RouterState| -> Vec<PeerId> {
+            state.peers().iter().map(|a| a.peer_id().clone()).collect()
+        };
+        assert_eq!(ids(&restored_state), ids(&state));
+
+        for (addr, expected) in [(&double_addr, 10), (&triple_addr, 15)] {
+            let addr = addr.with_host(restored.host_address());
+            let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+                &addr,
+                TcpNetLayer::new(),
+            );
+
+            let res = remote.send(Message::Task(5)).await.unwrap();
+            assert!(matches!(res, Ok(Reply::Task(n)) if n == expected));
+        }
+    }
+
+    #[tokio::test]
+    async fn restore_duplicate_peers() {
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 2 })
+            .await
+            .unwrap();
+
+        let addr = ActorAddress::new::<TcpNetLayer>("127.0.0.1:9000").unwrap();
+        let state = RouterState {
+            peers: vec![addr.clone(), addr.clone()],
+        };
+        let handles = HashMap::from([(addr.peer_id().clone(), handle)]);
+
+        let res = Router::restore(
+            TcpNetLayer::new(),
+            Some(RouterOpts::default()),
+            state,
+            handles,
+        )
+        .await;
+        assert!(matches!(res, Err(Error::DuplicatePeers(ids)) if ids == [addr.peer_id().clone()]));
+    }
+
+    #[test]
+    fn is_ready() {
+        let addr = ActorAddress::new::<TcpNetLayer>("127.0.0.1:9000").unwrap();
+        let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        assert!(remote.is_ready());
+    }
+
+    #[tokio::test]
+    async fn update_address() {
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let (_, first) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 2 })
+            .await
+            .unwrap();
+        let (_, second) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+
+        let first_addr = router.attach(first).await.unwrap();
+        let second_addr = router.attach(second).await.unwrap();
+
+        let mut remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &first_addr,
+            TcpNetLayer::new(),
+        );
+
+        let res = remote.send(Message::Task(5)).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Task(10))));
+
+        let wrong_proto =
+            ActorAddress::try_parse(&second_addr.to_string().replacen("tcp", "tor", 1)).unwrap();
+        assert!(matches!(
+            remote.update_address(&wrong_proto),
+            Err(Error::Address(_))
+        ));
+        assert_eq!(remote.addr().to_string(), first_addr.to_string());
+
+        remote.update_address(&second_addr).unwrap();
+        assert_eq!(remote.addr().to_string(), second_addr.to_string());
+
+        let res = remote.send(Message::Task(5)).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Task(15))));
+    }
+
+    #[tokio::test]
+    async fn request_id() {
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 2 })
+            .await
+            .unwrap();
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+
+        let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        let (res, first) = remote.send_with_id(Message::Task(4)).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Task(8))));
+
+        let (res, second) = remote.send_with_id(Message::Task(5)).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Task(10))));
+
+        assert!(second > first);
+    }
+
+    #[tokio::test]
+    async fn tell() {
+        let count = Arc::new(AtomicU64::new(0));
+        let actor = SlowCounter {
+            count: count.clone(),
+        };
+
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(actor)
+            .await
+            .unwrap();
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+
+        let remote = RemoteHandle::<u64, u64, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        let res = tokio::time::timeout(Duration::from_millis(200), remote.tell(500))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(res, Ok(Reply::Accepted)));
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+
+        tokio::time::sleep(Duration::from_millis(700)).await;
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn busy() {
+        let (_, handle) =
+            remote::spawn_untyped_with_capacity::<_, _, _, BitcodeDencoder>(Sleeper, 1)
+                .await
+                .unwrap();
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+
+        let remote = RemoteHandle::<u64, u64, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        // one in flight, one queued
+        let mut pending = vec![];
+        for _ in 0..2 {
+            let remote = remote.clone();
+            pending.push(tokio::spawn(async move {
+                remote.send(Message::Task(500)).await
+            }));
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        let res = remote.send(Message::Task(500)).await.unwrap();
+        assert!(matches!(res, Err(MsgError::Busy)));
+
+        let res = remote.describe().await;
+        assert!(matches!(
+            res,
+            Err(Error::WithContext { source, .. })
+                if matches!(&*source, Error::Recv(ctx) if ctx.contains("actor busy"))
+        ));
+
+        for res in pending {
+            assert!(matches!(res.await.unwrap(), Ok(Ok(Reply::Task(500)))));
+        }
+    }
+
+    #[test]
+    fn opts_builder() {
+        let opts = RouterOpts::builder()
+            .read_timeout(1000)
+            .channel_capacity(16)
+            .build()
+            .unwrap();
+        let defaults = RouterOpts::default();
+
+        assert_eq!(opts.msg_read_timeout(), 1000);
+        assert_eq!(opts.channel_capacity(), 16);
+        assert_eq!(opts.max_msg_size(), defaults.max_msg_size());
+        assert_eq!(opts.await_ready(), defaults.await_ready());
+        assert_eq!(opts.read_chunk_size(), defaults.read_chunk_size());
+        assert_eq!(opts.advertised_host(), None);
+        assert_eq!(opts.min_client_version(), defaults.min_client_version());
+
+        let res = RouterOpts::builder().max_msg_size(0).build();
+        assert!(matches!(res, Err(Error::InvalidOpts(_))));
+    }
+
+    #[test]
+    fn max_msg_size_bounds() {
+        let logs = LogCapture::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        assert_eq!(RouterOpts::new(5000, 0).max_msg_size(), 1);
+        assert_eq!(
+            RouterOpts::new(5000, u32::MAX).max_msg_size(),
+            MAX_MSG_SIZE_LIMIT
+        );
+        assert_eq!(
+            RouterOpts::new(5000, MAX_MSG_SIZE_LIMIT).max_msg_size(),
+            MAX_MSG_SIZE_LIMIT
+        );
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(logs.matches("out of bounds").count(), 2);
+
+        let res = RouterOpts::builder()
+            .max_msg_size(MAX_MSG_SIZE_LIMIT + 1)
+            .build();
+        assert!(matches!(res, Err(Error::InvalidOpts(_))));
+    }
+
+    #[tokio::test]
+    async fn max_peers() {
+        let opts = RouterOpts::builder().max_peers(1).build().unwrap();
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(opts))
+            .await
+            .unwrap();
+
+        let (_, first) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+        let (_, second) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 4 })
+            .await
+            .unwrap();
+
+        let addr = router.attach(first).await.unwrap();
+
+        let res = router.attach(second.clone()).await;
+        assert!(matches!(res, Err(Error::CapacityReached)));
+
+        router.revoke(&addr).await.unwrap();
+        router.attach(second).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn unexpected_reply() {
+        // router loop stand-in answering everything the wrong way
+        let (sender, mut receiver) =
+            mpsc::channel::<(RouterMessage, oneshot::Sender<Result<RouterReply, Error>>)>(8);
+        tokio::spawn(async move {
+            while let Some((command, reply)) = receiver.recv().await {
+                let _ = match command {
+                    RouterMessage::Attach { .. } | RouterMessage::Revoke(_) => {
+                        reply.send(Ok(RouterReply::Accepted))
+                    }
+                    _ => reply.send(Ok(RouterReply::Address(
+                        ActorAddress::new::<TcpNetLayer>("127.0.0.1:1").unwrap(),
+                    ))),
+                };
+            }
+        });
+
+        let router = RouterHandle {
+            host_address: "127.0.0.1:1".into(),
+            proto_name: "tcp",
+            sender,
+        };
+
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+        let addr = ActorAddress::new::<TcpNetLayer>("127.0.0.1:1").unwrap();
+
+        assert!(matches!(
+            router.attach(handle).await,
+            Err(Error::Protocol(_))
+        ));
+        assert!(matches!(
+            router.revoke(&addr).await,
+            Err(Error::Protocol(_))
+        ));
+        assert!(matches!(
+            router.update_opts(RouterOpts::default()).await,
+            Err(Error::Protocol(_))
+        ));
+        assert!(matches!(router.metrics().await, Err(Error::Protocol(_))));
+        assert!(matches!(router.stop().await, Err(Error::Protocol(_))));
+    }
+
+    /// TCP layer that never finishes initializing
+    struct StuckLayer(TcpNetLayer);
+
+    impl NetLayer for StuckLayer {
+        type Error = TcpError;
+
+        fn name() -> &'static str {
+            "tcp"
+        }
+
+        async fn connect(&self, addr: &str) -> Result<impl AsyncMsgStream, Self::Error> {
+            self.0.connect(addr).await
+        }
+
+        async fn init(&mut self) -> Result<(), Self::Error> {
+            std::future::pending().await
+        }
+
+        async fn accept(&self) -> Result<impl AsyncMsgStream, Self::Error> {
+            self.0.accept().await
+        }
+
+        async fn address(&self) -> Result<String, Self::Error> {
+            self.0.address().await
+        }
+    }
+
+    #[tokio::test]
+    async fn init_timeout() {
+        let opts = RouterOpts::builder().init_timeout(100).build().unwrap();
+
+        let res = tokio::time::timeout(
+            Duration::from_millis(1000),
+            Router::with_netlayer(StuckLayer(TcpNetLayer::new()), Some(opts)),
+        )
+        .await
+        .expect("router construction should time out on its own");
+
+        assert!(matches!(res, Err(Error::Init(_))));
+    }
+
+    /// tells whether its input is even
+    struct Parity;
+
+    impl Actor<u32, bool, SomeError> for Parity {
+        async fn handler(&self, input: u32, _ctx: &Context) -> Result<bool, SomeError> {
+            Ok(input.is_multiple_of(2))
+        }
+    }
+
+    #[tokio::test]
+    async fn broadcast() {
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let (_, mult) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+        let (_, parity) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Parity)
+            .await
+            .unwrap();
+
+        let mult_addr = router.attach(mult).await.unwrap();
+        let parity_addr = router.attach(parity).await.unwrap();
+
+        let replies = router
+            .broadcast::<u32, BitcodeDencoder>(Message::Task(5))
+            .await
+            .unwrap();
+        assert_eq!(replies.len(), 2);
+
+        for reply in replies {
+            if reply.peer_id() == mult_addr.peer_id() {
+                let res = reply
+                    .decode_as::<u32, SomeError, BitcodeDencoder>()
+                    .unwrap();
+                assert!(matches!(res, Ok(Reply::Task(15))));
+            } else {
+                assert_eq!(reply.peer_id(), parity_addr.peer_id());
+
+                let res = reply
+                    .decode_as::<bool, SomeError, BitcodeDencoder>()
+                    .unwrap();
+                assert!(matches!(res, Ok(Reply::Task(false))));
+            }
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    #[derive(Debug, Default)]
+    struct CapturingRecorder(std::sync::Mutex<Vec<String>>);
+
+    #[cfg(feature = "metrics")]
+    impl metrics::Recorder for CapturingRecorder {
+        fn describe_counter(
+            &self,
+            _: metrics::KeyName,
+            _: Option<metrics::Unit>,
+            _: metrics::SharedString,
+        ) {
+        }
+
+        fn describe_gauge(
+            &self,
+            _: metrics::KeyName,
+            _: Option<metrics::Unit>,
+            _: metrics::SharedString,
+        ) {
+        }
+
+        fn describe_histogram(
+            &self,
+            _: metrics::KeyName,
+            _: Option<metrics::Unit>,
+            _: metrics::SharedString,
+        ) {
+        }
+
+        fn register_counter(
+            &self,
+            key: &metrics::Key,
+            _: &metrics::Metadata<'_>,
+        ) -> metrics::Counter {
+            self.0.lock().unwrap().push(key.name().to_owned());
+            metrics::Counter::noop()
+        }
+
+        fn register_gauge(&self, key: &metrics::Key, _: &metrics::Metadata<'_>) -> metrics::Gauge {
+            self.0.lock().unwrap().push(key.name().to_owned());
+            metrics::Gauge::noop()
+        }
+
+        fn register_histogram(
+            &self,
+            key: &metrics::Key,
+            _: &metrics::Metadata<'_>,
+        ) -> metrics::Histogram {
+            self.0.lock().unwrap().push(key.name().to_owned());
+            metrics::Histogram::noop()
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    #[tokio::test]
+    async fn metrics_exported() {
+        use super::{METRIC_CONNECTIONS, METRIC_DROPPED, METRIC_HANDLER_SECONDS, METRIC_RELAYED};
+
+        let recorder: &'static CapturingRecorder = Box::leak(Box::default());
+        metrics::set_global_recorder(recorder).unwrap();
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+        let addr = router.attach(handle).await.unwrap();
+
+        let remote =
+            RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, _>::new(&addr, TcpNetLayer::new());
+        remote.send(Message::Task(5)).await.unwrap().unwrap();
+
+        router.revoke(&addr).await.unwrap();
+        assert!(remote.send(Message::Task(5)).await.is_err());
+
+        // give the router a moment to record the dropped connection
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let names = recorder.0.lock().unwrap().clone();
+        for name in [
+            METRIC_CONNECTIONS,
+            METRIC_RELAYED,
+            METRIC_DROPPED,
+            METRIC_HANDLER_SECONDS,
+        ] {
+            assert!(names.iter().any(|n| n == name), "{name} not recorded");
+        }
+    }
+
+    /// adds up everything it's sent as mutations
+    struct Accumulator {
+        total: u32,
+        delay: Duration,
+    }
+
+    impl Actor<u32, u32, SomeError> for Accumulator {
+        async fn handler(&self, _input: u32, _ctx: &Context) -> Result<u32, SomeError> {
+            Ok(self.total)
+        }
+
+        async fn handler_mut(
+            &mut self,
+            input: u32,
+            _ctx: &Context,
+        ) -> Result<Option<u32>, SomeError> {
+            tokio::time::sleep(self.delay).await;
+            self.total += input;
+
+            Ok(Some(self.total))
+        }
+    }
+
+    #[tokio::test]
+    async fn idempotency_key() {
+        let (_, mut handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Accumulator {
+            total: 0,
+            delay: Duration::ZERO,
+        })
+        .await
+        .unwrap();
+        handle.allow_mut(true);
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+        let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        let first = remote
+            .send_idempotent(Message::TaskMut(5), b"deposit-1")
+            .await
+            .unwrap();
+        let second = remote
+            .send_idempotent(Message::TaskMut(5), b"deposit-1")
+            .await
+            .unwrap();
+
+        assert!(matches!(first, Ok(Reply::Task(5))));
+        assert!(matches!(second, Ok(Reply::Task(5))));
+
+        let res = remote.send(Message::Task(0)).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Task(5))));
+
+        // a different key is a different request
+        let res = remote
+            .send_idempotent(Message::TaskMut(5), b"deposit-2")
+            .await
+            .unwrap();
+        assert!(matches!(res, Ok(Reply::Task(10))));
+    }
+
+    #[test]
+    fn reply_cache_bounded() {
+        let ttl = Duration::from_secs(60);
+        let key = |k: &[u8]| (PeerId::new_from_bytes(b"peer"), k.to_vec());
 
-        stream.write_u16(id_len).await.map_err(|err| {
-            tracing::error!("remote handle: failed to send peer ID size - {err}");
-            Error::Send(err.to_string())
-        })?;
+        let done = |k: u8| {
+            CachedReply::Done(SentReply {
+                chunks: vec![],
+                reply: vec![k],
+            })
+        };
 
-        stream.write_all(id.bytes()).await.map_err(|err| {
-            tracing::error!("remote handle: failed to send peer ID - {err}");
-            Error::Send(err.to_string())
-        })?;
+        let mut cache = ReplyCache::default();
+        for k in 0..4u8 {
+            cache.insert(key(&[k]), done(k), ttl, 2);
+        }
 
-        let bytes = D::encode(msg).map_err(Error::Serialize)?;
-        stream.write_u32(bytes.len() as u32).await.map_err(|err| {
-            tracing::error!("remote handle: failed to send message size - {err}");
-            Error::Send(err.to_string())
-        })?;
+        assert_eq!(cache.replies.len(), 2);
+        assert!(cache.get(&key(&[0]), ttl).is_none());
+        assert_eq!(
+            cache.get(&key(&[3]), ttl).map(|sent| sent.reply),
+            Some(vec![3])
+        );
 
-        stream.write_all(&bytes).await.map_err(|err| {
-            tracing::error!("remote handle: failed to send message - {err}");
-            Error::Send(err.to_string())
-        })?;
+        // expired replies aren't replayed, and make room first
+        assert!(cache.get(&key(&[3]), Duration::ZERO).is_none());
+        cache.insert(key(&[4]), done(4), Duration::ZERO, 2);
+        assert_eq!(cache.replies.len(), 1);
+    }
 
-        stream.flush().await.map_err(|err| {
-            tracing::error!("remote handle: failed to flush message - {err}");
-            Error::Send(err.to_string())
-        })?;
+    #[test]
+    fn reply_cache_claims() {
+        let ttl = Duration::from_secs(60);
+        let key = (PeerId::new_from_bytes(b"peer"), b"k".to_vec());
 
-        let size = stream.read_u32().await.map_err(|err| {
-            tracing::error!("remote handle: failed to receive message size - {err}");
-            Error::Recv(err.to_string())
-        })?;
+        let mut cache = ReplyCache::default();
+        let Claim::Run { claim, sender } = cache.claim(&key, ttl, 8) else {
+            panic!("first claim should run");
+        };
 
-        let mut res_buffer = vec![0; size as usize];
-        stream.read_exact(&mut res_buffer).await.map_err(|err| {
-            tracing::error!("remote handle: failed to receive message - {err}");
-            Error::Recv(err.to_string())
-        })?;
+        // repeats wait on the run, which doesn't expire like replies do
+        assert!(matches!(
+            cache.claim(&key, Duration::ZERO, 8),
+            Claim::Wait(_)
+        ));
 
-        D::decode(res_buffer).map_err(Error::Serialize)
-    }
+        // an abandoned run makes way for the next repeat
+        drop(sender);
+        cache.abandon(&key, claim);
+        let Claim::Run { claim: retry, .. } = cache.claim(&key, ttl, 8) else {
+            panic!("claim after abandoning should run");
+        };
 
-    /// [`ActorAddress`] pointed to by this handle
-    pub fn addr(&self) -> &ActorAddress {
-        &self.address
+        // stale runs can't abandon a newer one
+        cache.abandon(&key, claim);
+        assert!(matches!(cache.claim(&key, ttl, 8), Claim::Wait(_)));
+        assert_ne!(claim, retry);
     }
-}
 
-#[derive(Debug)]
-enum RouterMessage {
-    Stop,
-    Attach {
-        handle: UntypedHandle,
-        peer_id: Option<PeerId>,
-    },
-    Revoke(ActorAddress),
-}
+    #[test]
+    fn reply_cache_keeps_runs() {
+        let ttl = Duration::from_secs(60);
+        let key = |k: &[u8]| (PeerId::new_from_bytes(b"peer"), k.to_vec());
 
-enum RouterReply {
-    Accepted,
-    Address(ActorAddress),
-}
+        let mut cache = ReplyCache::default();
+        let Claim::Run { .. } = cache.claim(&key(b"a"), ttl, 1) else {
+            panic!("first claim should run");
+        };
 
-///
-/// errors when creating a routing, or messaging an actor with it
-///
-#[allow(missing_docs)]
-#[derive(Debug)]
-pub enum Error {
-    Init(String),
-    Connect(String),
-    Serialize(dencoder::Error),
-    Send(String),
-    Recv(String),
-    Address(address::Error),
-}
+        // a full cache of runs leaves the new key uncached rather than dropping a run
+        assert!(matches!(cache.claim(&key(b"b"), ttl, 1), Claim::Run { .. }));
+        assert!(matches!(cache.claim(&key(b"a"), ttl, 1), Claim::Wait(_)));
 
-impl Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Error::Init(ctx) => write!(f, "failed to init router: {ctx}"),
-            Error::Connect(ctx) => write!(f, "failed to connect to endpoint: {ctx}"),
-            Error::Serialize(ctx) => write!(f, "failed to encode/decode message: {ctx}"),
-            Error::Send(ctx) => write!(f, "failed to send message: {ctx}"),
-            Error::Recv(ctx) => write!(f, "failed to receive message: {ctx}"),
-            Error::Address(ctx) => write!(f, "failed to create address: {ctx}"),
-        }
+        // finished replies still make room
+        cache.insert(
+            key(b"a"),
+            CachedReply::Done(SentReply {
+                chunks: vec![],
+                reply: vec![1],
+            }),
+            ttl,
+            1,
+        );
+        let Claim::Run { .. } = cache.claim(&key(b"c"), ttl, 1) else {
+            panic!("claim after a reply should run");
+        };
+        assert!(matches!(cache.claim(&key(b"c"), ttl, 1), Claim::Wait(_)));
+        assert!(cache.get(&key(b"a"), ttl).is_none());
     }
-}
 
-impl std::error::Error for Error {}
+    #[tokio::test]
+    async fn idempotency_key_in_flight() {
+        let (_, mut handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Accumulator {
+            total: 0,
+            delay: Duration::from_millis(200),
+        })
+        .await
+        .unwrap();
+        handle.allow_mut(true);
 
-#[cfg(test)]
-mod tests {
-    use crate::{
-        actors::{
-            remote::{
-                self,
-                address::PeerId,
-                dencoder::bitcode::BitcodeDencoder,
-                netlayer::tcp_layer::TcpNetLayer,
-                router::{RemoteHandle, Router, RouterOpts},
-            },
-            tests::{Mult, SomeError},
-        },
-        messaging::{Message, Reply},
-    };
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let addr = router.attach(handle).await.unwrap();
+        let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        // a retry while the first attempt is still running
+        let (first, second) = tokio::join!(
+            remote.send_idempotent(Message::TaskMut(5), b"k"),
+            remote.send_idempotent(Message::TaskMut(5), b"k")
+        );
+        assert!(matches!(first, Ok(Ok(Reply::Task(5)))));
+        assert!(matches!(second, Ok(Ok(Reply::Task(5)))));
+
+        let total = remote.send(Message::Task(0)).await.unwrap();
+        assert!(matches!(total, Ok(Reply::Task(5))));
+    }
 
     #[tokio::test]
-    async fn spawn_and_message() {
-        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+    async fn idempotency_key_streaming() {
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Chunker)
             .await
             .unwrap();
 
@@ -564,18 +4194,89 @@ mod tests {
             .unwrap();
 
         let addr = router.attach(handle).await.unwrap();
-
-        let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+        let remote = RemoteHandle::<u8, (), SomeError, BitcodeDencoder, TcpNetLayer>::new(
             &addr,
             TcpNetLayer::new(),
         );
 
-        let res = remote.send(Message::Task(5)).await.unwrap();
-        assert!(matches!(res, Ok(Reply::Task(15))));
+        let msg = BitcodeDencoder::encode(Message::<u8>::Stream(2)).unwrap();
+        let first = remote.send_raw(&msg, b"k").await.unwrap();
+        let second = remote.send_raw(&msg, b"k").await.unwrap();
+
+        // replays come with the chunks streamed the first time
+        assert_eq!(first.streamed, [[0; 4], [1; 4]].concat());
+        assert_eq!(second.streamed, first.streamed);
+        assert_eq!(second.body, first.body);
+    }
+
+    /// TCP layer failing its first few accepts, recording when, and hanging up on its first few
+    /// dialed requests right after reading them
+    struct FlakyLayer {
+        inner: TcpNetLayer,
+        failures_left: AtomicU64,
+        failed_at: Arc<std::sync::Mutex<Vec<Instant>>>,
+        hang_ups_left: AtomicU64,
+    }
+
+    impl NetLayer for FlakyLayer {
+        type Error = TcpError;
+
+        fn name() -> &'static str {
+            "tcp"
+        }
+
+        async fn connect(&self, addr: &str) -> Result<impl AsyncMsgStream, Self::Error> {
+            let hanging_up = self
+                .hang_ups_left
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+
+            if hanging_up {
+                let (stream, mut peer) = tokio::io::duplex(1024);
+                tokio::spawn(async move {
+                    let _version = peer.read_u16().await?;
+                    let id_len = peer.read_u16().await?;
+                    peer.read_exact(&mut vec![0; id_len as usize]).await?;
+                    let msg_len = peer.read_u32().await?;
+                    peer.read_exact(&mut vec![0; msg_len as usize]).await?;
+                    let key_len = peer.read_u16().await?;
+                    peer.read_exact(&mut vec![0; key_len as usize]).await?;
+
+                    // dropping our end hangs up without a reply
+                    Ok::<_, std::io::Error>(())
+                });
+
+                return Ok(Either::Left(stream));
+            }
+
+            Ok(Either::Right(self.inner.connect(addr).await?))
+        }
+
+        async fn init(&mut self) -> Result<(), Self::Error> {
+            self.inner.init().await
+        }
+
+        async fn accept(&self) -> Result<impl AsyncMsgStream, Self::Error> {
+            let failing = self
+                .failures_left
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+
+            if failing {
+                self.failed_at.lock().unwrap().push(Instant::now());
+                return Err(TcpError::Accept("too many open files".into()));
+            }
+
+            self.inner.accept().await
+        }
+
+        async fn address(&self) -> Result<String, Self::Error> {
+            self.inner.address().await
+        }
     }
 
     #[tokio::test]
-    async fn spawn_with_id() {
+    async fn auto_redial_after_hang_up() {
         let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
             .await
             .unwrap();
@@ -584,13 +4285,54 @@ mod tests {
             .await
             .unwrap();
 
-        let peer_id = PeerId::new().unwrap();
-        let addr = router
-            .attach_with_id(handle, peer_id.clone())
+        let addr = router.attach(handle).await.unwrap();
+
+        let flaky_layer = || FlakyLayer {
+            inner: TcpNetLayer::new(),
+            failures_left: AtomicU64::new(0),
+            failed_at: Arc::new(std::sync::Mutex::new(vec![])),
+            hang_ups_left: AtomicU64::new(1),
+        };
+
+        let remote =
+            RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, _>::new(&addr, flaky_layer());
+        let res = remote.send(Message::Task(5)).await;
+        assert!(matches!(
+            res,
+            Err(Error::WithContext { source, .. }) if matches!(*source, Error::Disconnected)
+        ));
+
+        let remote =
+            RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, _>::new(&addr, flaky_layer())
+                .with_auto_redial(true);
+        let res = remote.send(Message::Task(5)).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Task(15))));
+    }
+
+    #[tokio::test]
+    async fn accept_backoff() {
+        let failed_at = Arc::new(std::sync::Mutex::new(vec![]));
+        let layer = FlakyLayer {
+            inner: TcpNetLayer::new(),
+            failures_left: AtomicU64::new(4),
+            failed_at: failed_at.clone(),
+            hang_ups_left: AtomicU64::new(0),
+        };
+
+        let opts = RouterOpts::builder()
+            .accept_backoff(20)
+            .max_accept_backoff(40)
+            .build()
+            .unwrap();
+
+        let router = Router::with_netlayer(layer, Some(opts)).await.unwrap();
+
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
             .await
             .unwrap();
 
-        assert_eq!(peer_id, *addr.peer_id());
+        // still answering commands while backing off
+        let addr = router.attach(handle).await.unwrap();
 
         let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
             &addr,
@@ -599,10 +4341,38 @@ mod tests {
 
         let res = remote.send(Message::Task(5)).await.unwrap();
         assert!(matches!(res, Ok(Reply::Task(15))));
+
+        // 20, 40 and 40 (capped) milliseconds apart rather than spinning
+        let failed_at = failed_at.lock().unwrap().clone();
+        assert_eq!(failed_at.len(), 4);
+
+        let gaps: Vec<Duration> = failed_at.windows(2).map(|w| w[1] - w[0]).collect();
+        assert!(gaps[0] >= Duration::from_millis(20));
+        assert!(gaps[1] >= Duration::from_millis(40));
+        assert!(gaps[2] >= Duration::from_millis(40));
+    }
+
+    #[test]
+    fn accept_backoff_after() {
+        let opts = RouterOpts::builder()
+            .accept_backoff(10)
+            .max_accept_backoff(50)
+            .build()
+            .unwrap();
+
+        let backoffs: Vec<u64> = (1..=5)
+            .map(|failures| opts.accept_backoff_after(failures).as_millis() as u64)
+            .collect();
+
+        assert_eq!(backoffs, [10, 20, 40, 50, 50]);
+        assert_eq!(
+            opts.accept_backoff_after(u32::MAX),
+            Duration::from_millis(50)
+        );
     }
 
     #[tokio::test]
-    async fn ping() {
+    async fn reply_type_mismatch() {
         let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
             .await
             .unwrap();
@@ -612,63 +4382,258 @@ mod tests {
             .unwrap();
 
         let addr = router.attach(handle).await.unwrap();
+        let remote = RemoteHandle::<u32, String, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        let err = remote.send(Message::Task(5)).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::ReplyType {
+                expected: "alloc::string::String",
+                ..
+            }
+        ));
+        assert!(err.to_string().contains("String"));
+    }
 
+    #[test]
+    fn into_future() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        rt.block_on(async {
+            let (router, router_loop) =
+                Router::into_future(TcpNetLayer::new(), Some(RouterOpts::default()))
+                    .await
+                    .unwrap();
+
+            let client = async {
+                let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+                    .await
+                    .unwrap();
+                let addr = router.attach(handle).await.unwrap();
+
+                let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+                    &addr,
+                    TcpNetLayer::new(),
+                );
+
+                remote.send(Message::Task(5)).await.unwrap()
+            };
+
+            let res = tokio::select! {
+                _ = router_loop => panic!("router loop ended on its own"),
+                res = client => res,
+            };
+            assert!(matches!(res, Ok(Reply::Task(15))));
+
+            // the loop was dropped along with the select
+            assert!(matches!(router.metrics().await, Err(Error::RouterStopped)));
+        });
+    }
+
+    #[tokio::test]
+    async fn attach_with_label() {
+        let logs = LogCapture::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        // current-thread runtime, so the router's tasks log here too
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let addr = router.attach_with_label(handle, "billing").await.unwrap();
         let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
             &addr,
             TcpNetLayer::new(),
         );
 
-        let res = remote.send(Message::Ping).await.unwrap();
-        assert!(matches!(res, Ok(Reply::Accepted)));
+        let res = remote.send(Message::Task(5)).await.unwrap();
+        assert!(matches!(res, Ok(Reply::Task(15))));
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(
+            logs.lines()
+                .any(|line| line.contains("label=\"billing\"") && line.contains("relaying"))
+        );
     }
 
     #[tokio::test]
-    async fn stop() {
-        let (_, mut handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+    async fn attach_lazy() {
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
             .await
             .unwrap();
 
-        handle.allow_stop(true);
+        let spawned = Arc::new(AtomicU64::new(0));
+        let counter = spawned.clone();
 
-        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+        let addr = router
+            .attach_lazy(move || async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                // long enough for both requests to be waiting on it
+                tokio::time::sleep(Duration::from_millis(100)).await;
+
+                let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+                    .await
+                    .unwrap();
+
+                handle
+            })
             .await
             .unwrap();
 
-        let addr = router.attach(handle).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(spawned.load(Ordering::SeqCst), 0);
 
         let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
             &addr,
             TcpNetLayer::new(),
         );
 
-        let res = remote.send(Message::Stop).await.unwrap();
-        assert!(matches!(res, Ok(Reply::Accepted)));
+        let (a, b) = tokio::join!(remote.send(Message::Task(5)), remote.send(Message::Task(7)));
+        assert!(matches!(a, Ok(Ok(Reply::Task(15)))));
+        assert!(matches!(b, Ok(Ok(Reply::Task(21)))));
 
-        remote.send(Message::Ping).await.unwrap_err();
+        let res = remote.send(Message::Task(2)).await;
+        assert!(matches!(res, Ok(Ok(Reply::Task(6)))));
+
+        assert_eq!(spawned.load(Ordering::SeqCst), 1);
     }
 
     #[tokio::test]
-    async fn revoke() {
+    async fn attach_lazy_broadcast() {
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
         let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
             .await
             .unwrap();
+        router.attach(handle).await.unwrap();
+
+        let spawned = Arc::new(AtomicU64::new(0));
+        let counter = spawned.clone();
+
+        let addr = router
+            .attach_lazy(move || async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+
+                let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+                    .await
+                    .unwrap();
+
+                handle
+            })
+            .await
+            .unwrap();
+
+        let replies = router
+            .broadcast::<u32, BitcodeDencoder>(Message::Task(5))
+            .await
+            .unwrap();
+        assert_eq!(replies.len(), 1);
+        assert_eq!(spawned.load(Ordering::SeqCst), 0);
+
+        let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+        remote.send(Message::Task(5)).await.unwrap().unwrap();
+
+        let replies = router
+            .broadcast::<u32, BitcodeDencoder>(Message::Task(5))
+            .await
+            .unwrap();
+        assert_eq!(replies.len(), 2);
+        assert_eq!(spawned.load(Ordering::SeqCst), 1);
+    }
 
+    #[tokio::test]
+    async fn attach_lazy_panic() {
         let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
             .await
             .unwrap();
 
+        let addr = router
+            .attach_lazy(|| async {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+
+                panic!("factory failed");
+            })
+            .await
+            .unwrap();
+
+        let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        let (a, b) = tokio::join!(remote.send(Message::Task(5)), remote.send(Message::Task(7)));
+        assert!(a.is_err());
+        assert!(b.is_err());
+        assert!(remote.send(Message::Task(2)).await.is_err());
+
+        // the router keeps serving other actors
+        let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
         let addr = router.attach(handle).await.unwrap();
 
         let remote = RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TcpNetLayer>::new(
             &addr,
             TcpNetLayer::new(),
         );
+        let res = remote.send(Message::Task(2)).await;
+        assert!(matches!(res, Ok(Ok(Reply::Task(6)))));
 
-        let res = remote.send(Message::Ping).await.unwrap();
-        assert!(matches!(res, Ok(Reply::Accepted)));
+        let replies = router
+            .broadcast::<u32, BitcodeDencoder>(Message::Task(5))
+            .await
+            .unwrap();
+        assert_eq!(replies.len(), 1);
+    }
 
-        router.revoke(&addr).await.unwrap();
+    #[tokio::test]
+    async fn broadcast_typed() {
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
 
-        remote.send(Message::Ping).await.unwrap_err();
+        let mut addrs = vec![];
+        for _ in 0..3 {
+            let (_, handle) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+                .await
+                .unwrap();
+            addrs.push(router.attach(handle).await.unwrap());
+        }
+
+        let (replies, failed) = router
+            .broadcast_typed::<u32, u32, SomeError, BitcodeDencoder>(Message::Task(5))
+            .await
+            .unwrap();
+
+        assert!(failed.is_empty());
+        assert_eq!(replies.len(), 3);
+
+        for addr in addrs {
+            assert!(matches!(
+                replies.get(addr.peer_id()),
+                Some(Ok(Reply::Task(15)))
+            ));
+        }
     }
 }
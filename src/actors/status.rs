@@ -0,0 +1,105 @@
+//!
+//! ready-made actor for router introspection
+//!
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    Actor, Context,
+    remote::router::{RouterHandle, RouterMetrics},
+};
+
+///
+/// actor reporting the state of a router, meant to be attached to it
+/// as a well-known introspection capability
+///
+#[derive(Debug)]
+pub struct StatusActor {
+    router: RouterHandle,
+}
+
+impl StatusActor {
+    /// create a status actor reporting on this router
+    pub fn new(router: RouterHandle) -> Self {
+        Self { router }
+    }
+}
+
+impl Actor<(), RouterMetrics, Error> for StatusActor {
+    async fn handler(&self, _input: (), _ctx: &Context) -> Result<RouterMetrics, Error> {
+        self.router
+            .metrics()
+            .await
+            .map_err(|e| Error::Router(e.to_string()))
+    }
+}
+
+///
+/// errors when querying a router's status
+///
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Error {
+    Router(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Router(ctx) => write!(f, "failed to query router: {ctx}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use crate::{
+        actors::{
+            remote::{
+                self,
+                dencoder::bitcode::BitcodeDencoder,
+                netlayer::tcp_layer::TcpNetLayer,
+                router::{RemoteHandle, Router, RouterMetrics, RouterOpts},
+            },
+            tests::Mult,
+        },
+        messaging::{Message, Reply},
+    };
+
+    use super::{Error, StatusActor};
+
+    #[tokio::test]
+    async fn attached_count() {
+        let router = Router::with_netlayer(TcpNetLayer::new(), Some(RouterOpts::default()))
+            .await
+            .unwrap();
+
+        let (_, mult) = remote::spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 3 })
+            .await
+            .unwrap();
+        let (_, status) =
+            remote::spawn_untyped::<_, _, _, BitcodeDencoder>(StatusActor::new(router.clone()))
+                .await
+                .unwrap();
+
+        router.attach(mult).await.unwrap();
+        let addr = router.attach(status).await.unwrap();
+
+        let remote = RemoteHandle::<(), RouterMetrics, Error, BitcodeDencoder, TcpNetLayer>::new(
+            &addr,
+            TcpNetLayer::new(),
+        );
+
+        let metrics = match remote.send(Message::Task(())).await.unwrap() {
+            Ok(Reply::Task(metrics)) => metrics,
+            _ => panic!("expected router metrics"),
+        };
+
+        assert_eq!(2, metrics.attached());
+        assert_eq!(1, metrics.connections());
+    }
+}
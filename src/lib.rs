@@ -8,7 +8,7 @@
 //! # use serde::{Deserialize, Serialize};
 //! # use myriam::{
 //! #    actors::{
-//! #        Actor,
+//! #        Actor, Context,
 //! #        remote::{
 //! #            self,
 //! #            dencoder::bitcode::BitcodeDencoder,
@@ -33,7 +33,7 @@
 //! # }
 //! #
 //! impl Actor<u32, u32, SomeError> for Mult {
-//!     async fn handler(&self, input: u32) -> Result<u32, SomeError> {
+//!     async fn handler(&self, input: u32, _ctx: &Context) -> Result<u32, SomeError> {
 //!         Ok(input * self.a)
 //!     }
 //! }
@@ -84,6 +84,9 @@
 //! * `remote (default)`: support for remote messaging
 //! * `tcp (default)`: TCP test-only net layer
 //! * `tor (default)`: Tor net layer - requires a running and properly configured Tor router
+//! * `debug_wire`: capture raw request/reply bytes in `WireTrace`s
+//! * `tokio-console`: name spawned tasks for [tokio-console](https://github.com/tokio-rs/console) - requires building with `--cfg tokio_unstable`
+//! * `metrics`: record router metrics through the [`metrics`](https://docs.rs/metrics) crate facade
 //!
 //! # license
 //!
@@ -104,6 +107,7 @@
 
 pub mod actors;
 pub mod messaging;
+pub mod registry;
 
 pub(crate) mod utils;
 
@@ -6,11 +6,12 @@ use std::fmt::Display;
 
 #[cfg(feature = "remote")]
 use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
 
 ///
 /// actor message
 ///
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "remote", derive(Serialize, Deserialize))]
 pub enum Message<Input> {
     /// task request with known input
@@ -24,6 +25,24 @@ pub enum Message<Input> {
 
     /// stop this actor
     Stop,
+
+    /// task request whose output is discarded, accepted before the handler runs
+    Tell(Input),
+
+    /// ask this actor for its [`TypeSignature`]
+    Describe,
+
+    /// ask this actor for a snapshot of its state
+    Snapshot,
+
+    ///
+    /// task request whose output is written in chunks to a [`ReplyWriter`], then answered with
+    /// [`Reply::Accepted`].
+    ///
+    /// needs a streaming send (e.g. `LocalHandle::send_streaming`), refused with
+    /// [`MsgError::NotAllowed`] otherwise.
+    ///
+    Stream(Input),
 }
 
 ///
@@ -37,6 +56,82 @@ pub enum Reply<Output> {
 
     /// task output
     Task(Output),
+
+    /// type names of the actor's input, output and error
+    Describe(TypeSignature),
+
+    /// serialized snapshot of the actor's state
+    Snapshot(Vec<u8>),
+}
+
+///
+/// type names an actor was spawned with, for diagnosing mismatched handles
+///
+/// names come from [`std::any::type_name`], so they're meant for humans rather than strict comparison.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "remote", derive(Serialize, Deserialize))]
+pub struct TypeSignature {
+    input: String,
+    output: String,
+    error: String,
+}
+
+impl TypeSignature {
+    /// signature of an actor over these types
+    pub fn of<I, O, E>() -> Self {
+        Self {
+            input: std::any::type_name::<I>().to_string(),
+            output: std::any::type_name::<O>().to_string(),
+            error: std::any::type_name::<E>().to_string(),
+        }
+    }
+
+    /// input type name
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// output type name
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    /// error type name
+    pub fn error(&self) -> &str {
+        &self.error
+    }
+}
+
+///
+/// sending half of a streamed reply, handed to [`Actor::handler_streaming`](crate::actors::Actor::handler_streaming)
+///
+#[derive(Debug, Clone)]
+pub struct ReplyWriter {
+    sender: mpsc::Sender<Vec<u8>>,
+}
+
+impl ReplyWriter {
+    ///
+    /// create a writer and the receiver its chunks end up in, buffering up to `capacity` chunks
+    ///
+    pub fn channel(capacity: usize) -> (Self, mpsc::Receiver<Vec<u8>>) {
+        let (sender, receiver) = mpsc::channel(capacity.max(1));
+
+        (Self { sender }, receiver)
+    }
+
+    ///
+    /// write the next chunk of this reply, waiting while the buffer is full.
+    ///
+    /// fails with [`MsgError::Send`] once the receiving end is gone, e.g. the client hung up.
+    ///
+    pub async fn write<E>(&self, chunk: impl Into<Vec<u8>>) -> Result<(), MsgError<E>> {
+        self.sender
+            .send(chunk.into())
+            .await
+            .map_err(|e| MsgError::Send(e.to_string()))
+    }
 }
 
 ///
@@ -55,6 +150,9 @@ pub enum MsgError<Error> {
     Recv(String),
     Task(Error),
     NotAllowed,
+    Busy,
+    StopRefused,
+    NotSupported,
 }
 
 impl<E> Display for MsgError<E>
@@ -67,6 +165,9 @@ where
             MsgError::Recv(ctx) => write!(f, "failed to receive message: {ctx}"),
             MsgError::Task(err) => write!(f, "task failed: {err}"),
             MsgError::NotAllowed => write!(f, "message not allowed"),
+            MsgError::Busy => write!(f, "actor busy, try again later"),
+            MsgError::StopRefused => write!(f, "actor refused to stop"),
+            MsgError::NotSupported => write!(f, "message not supported by this actor"),
         }
     }
 }
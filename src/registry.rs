@@ -0,0 +1,91 @@
+//!
+//! process-wide registry of local actor handles, looked up by name
+//!
+
+use std::{
+    any::Any,
+    collections::HashMap,
+    sync::{OnceLock, RwLock},
+};
+
+use crate::actors::local::LocalHandle;
+
+type Registry = RwLock<HashMap<String, Box<dyn Any + Send + Sync>>>;
+
+static REGISTRY: OnceLock<Registry> = OnceLock::new();
+
+fn registry() -> &'static Registry {
+    REGISTRY.get_or_init(Default::default)
+}
+
+///
+/// register `handle` under `name`, replacing whatever was registered there before.
+///
+pub fn register<I, O, E>(name: impl Into<String>, handle: LocalHandle<I, O, E>)
+where
+    I: Send + 'static,
+    O: Send + 'static,
+    E: Send + 'static,
+{
+    registry()
+        .write()
+        .expect("poisoned lock")
+        .insert(name.into(), Box::new(handle));
+}
+
+///
+/// handle registered under `name`.
+///
+/// none if there's nothing under that name, or it's a handle over different types.
+///
+pub fn lookup<I, O, E>(name: &str) -> Option<LocalHandle<I, O, E>>
+where
+    I: Send + 'static,
+    O: Send + 'static,
+    E: Send + 'static,
+{
+    registry()
+        .read()
+        .expect("poisoned lock")
+        .get(name)?
+        .downcast_ref::<LocalHandle<I, O, E>>()
+        .cloned()
+}
+
+///
+/// remove whatever was registered under `name`, returning whether there was anything.
+///
+pub fn unregister(name: &str) -> bool {
+    registry()
+        .write()
+        .expect("poisoned lock")
+        .remove(name)
+        .is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        actors::{
+            local,
+            tests::{Mult, SomeError},
+        },
+        messaging::{Message, Reply},
+    };
+
+    #[tokio::test]
+    async fn register_and_lookup() {
+        let handle = local::spawn(Mult { a: 3 }).await.unwrap();
+        super::register("registry::mult", handle);
+
+        let handle = super::lookup::<u32, u32, SomeError>("registry::mult").unwrap();
+        let reply = handle.send(Message::Task(5)).await;
+        assert!(matches!(reply, Ok(Reply::Task(15))));
+
+        assert!(super::lookup::<u64, u32, SomeError>("registry::mult").is_none());
+        assert!(super::lookup::<u32, u32, SomeError>("registry::nothing").is_none());
+
+        assert!(super::unregister("registry::mult"));
+        assert!(super::lookup::<u32, u32, SomeError>("registry::mult").is_none());
+    }
+}
@@ -1,6 +1,33 @@
 use std::fmt::Display;
 
-use tokio::net::TcpListener;
+use tokio::{net::TcpListener, task::JoinHandle};
+
+///
+/// spawn a task, named after `name` so it's recognizable in tokio-console
+///
+#[cfg(all(feature = "tokio-console", tokio_unstable))]
+pub(crate) fn spawn_task<F>(name: &str, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    tokio::task::Builder::new()
+        .name(name)
+        .spawn(future)
+        .expect("failed to spawn task")
+}
+
+///
+/// spawn a task. names are only used with the `tokio-console` feature and `--cfg tokio_unstable`
+///
+#[cfg(not(all(feature = "tokio-console", tokio_unstable)))]
+pub(crate) fn spawn_task<F>(_name: &str, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    tokio::spawn(future)
+}
 
 pub async fn random_unused_port() -> Result<u16, Error> {
     let listener = TcpListener::bind("127.0.0.1:0")
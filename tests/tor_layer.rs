@@ -5,15 +5,22 @@
 //! * extremely slow since we have to establish two separate Tor circuits, so ignored by default
 //!
 
-use std::fmt::Display;
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
 
+use futures::StreamExt;
 use myriam::{
     actors::{
-        Actor,
+        Actor, Context,
         remote::{
             address::PeerId,
             dencoder::bitcode::BitcodeDencoder,
-            netlayer::tor_layer::{TorLayer, TorLayerConfig, TorLayerDirectories},
+            netlayer::{
+                NetLayer,
+                tor_layer::{TorLayer, TorLayerConfig, TorLayerDirectories},
+            },
             router::{RemoteHandle, Router, RouterOpts},
             spawn_untyped,
         },
@@ -53,6 +60,80 @@ async fn roundtrip() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[ignore]
+#[tokio::test]
+async fn bootstrap_progress() -> Result<(), Box<dyn std::error::Error>> {
+    let (layer, progress) =
+        TorLayer::new_with_progress("actor-3".to_string(), TorLayerConfig::new_from_port(2051));
+
+    let (layer, events) = tokio::join!(layer, progress.collect::<Vec<_>>());
+    layer?;
+
+    assert!(!events.is_empty());
+
+    Ok(())
+}
+
+#[ignore]
+#[tokio::test]
+async fn skip_reachability_wait() -> Result<(), Box<dyn std::error::Error>> {
+    let mut layer = TorLayer::new("actor-4".to_string(), TorLayerConfig::new_from_port(2052))
+        .await?
+        .with_reachability_timeout(Duration::ZERO);
+
+    let start = Instant::now();
+    layer.init().await?;
+    layer.ready().await?;
+
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(layer.address().await.is_ok());
+
+    Ok(())
+}
+
+#[ignore]
+#[tokio::test]
+async fn last_status() -> Result<(), Box<dyn std::error::Error>> {
+    let mut layer = TorLayer::new("actor-5".to_string(), TorLayerConfig::new_from_port(2053))
+        .await?
+        .with_reachability_timeout(Duration::ZERO);
+
+    assert!(layer.last_status().is_none());
+
+    layer.init().await?;
+
+    let status = layer.last_status().expect("service should be launched");
+    tracing::info!("onion service state: {:?}", status.state());
+
+    Ok(())
+}
+
+#[ignore]
+#[tokio::test]
+async fn advertised_port() -> Result<(), Box<dyn std::error::Error>> {
+    let tor_layer = TorLayer::new(
+        "actor-6".to_string(),
+        TorLayerConfig::new_with_ports(2054, 2055, None),
+    )
+    .await?;
+    let (_, untyped) = spawn_untyped::<_, _, _, BitcodeDencoder>(Mult { a: 15 }).await?;
+
+    let router_opts = RouterOpts::new(60_000, 5_000);
+    let router_handle = Router::with_netlayer(tor_layer, Some(router_opts)).await?;
+    let address = router_handle.attach(untyped).await?;
+
+    assert!(address.host().ends_with(":2055"));
+
+    let tor_layer = TorLayer::new_for_client("actor-7".to_string()).await?;
+    let remote_handle =
+        RemoteHandle::<u32, u32, SomeError, BitcodeDencoder, TorLayer>::new(&address, tor_layer);
+
+    let response = remote_handle.send(Message::Task(3)).await??;
+    assert!(matches!(response, Reply::Task(45)));
+
+    Ok(())
+}
+
 struct Mult {
     pub a: u32,
 }
@@ -67,7 +148,7 @@ impl Display for SomeError {
 }
 
 impl Actor<u32, u32, SomeError> for Mult {
-    async fn handler(&self, input: u32) -> Result<u32, SomeError> {
+    async fn handler(&self, input: u32, _ctx: &Context) -> Result<u32, SomeError> {
         Ok(input * self.a)
     }
 }